@@ -1,6 +1,10 @@
 // In src/cli.rs
 
-use clap::{Parser, Subcommand};
+use crate::commands::date::parse_date;
+use crate::commands::diff::{parse_similarity, WordDiffMode};
+use crate::commands::interpret_trailers::IfExists as InterpretTrailersIfExists;
+use crate::commands::log::{parse_pretty, DateMode, Pretty};
+use clap::{Args, Parser, Subcommand};
 
 /// Rit: A simple, Git-like version control system written in Rust.
 ///
@@ -36,53 +40,343 @@ pub enum Commands {
     ///
     /// Commits are permanent snapshots of your project's history. Each commit
     /// has a unique hash, an author, a timestamp, and a message.
-    #[command(after_help = "EXAMPLE:\n    rit commit -m \"feat: Implement the new login page\"")]
+    ///
+    /// A commit whose tree is the same as its parent's is refused unless
+    /// --allow-empty is given. --amend replaces the latest commit instead,
+    /// starting from its message (kept as it is with --no-edit).
+    ///
+    /// Without -m or -F the message is written in $RIT_EDITOR, core.editor,
+    /// $VISUAL or $EDITOR, starting from commit.template if set; lines
+    /// starting with '#' are dropped and an empty message aborts the commit.
+    ///
+    /// The author and committer come from user.name and user.email, unless
+    /// RIT_AUTHOR_NAME, RIT_AUTHOR_EMAIL, RIT_AUTHOR_DATE or their
+    /// RIT_COMMITTER_* counterparts are set.
+    #[command(after_help = "EXAMPLES:\n    rit commit\n    rit commit -m \"feat: Implement the new login page\"\n    rit commit -m \"fix: Handle empty input\" -m \"Closes #12.\"\n    rit commit -F notes.txt\n    rit commit -a -m \"fix: Handle empty input\"\n    rit commit --amend --no-edit\n    rit commit --fixup=HEAD~2\n    rit commit --allow-empty -m \"chore: Trigger CI\"\n    rit commit --author=\"Ada Lovelace <ada@example.com>\" -m \"docs: Add notes\"\n    rit commit -s --trailer \"Reviewed-by: Ann Dev <ann@example.com>\" -m \"fix: Typo\"")]
     Commit {
-        /// The commit message, describing the changes made
+        /// The commit message, describing the changes made. Repeat for more
+        /// paragraphs; without it an editor is opened
+        #[arg(short, long)]
+        message: Vec<String>,
+
+        /// Read the commit message from FILE (`-` for standard input)
+        #[arg(short = 'F', long, value_name = "FILE", conflicts_with = "message")]
+        file: Option<String>,
+
+        /// Use the existing message (e.g. with --amend) without opening an editor
+        #[arg(long)]
+        no_edit: bool,
+
+        /// Stage all modified and deleted tracked files before committing
         #[arg(short, long)]
-        message: String,
+        all: bool,
+
+        /// Replace the latest commit with a new one
+        #[arg(long)]
+        amend: bool,
+
+        /// Allow a commit that records no changes
+        #[arg(long)]
+        allow_empty: bool,
+
+        /// Make a "fixup!" commit for COMMIT, to be folded into it later
+        #[arg(long, value_name = "COMMIT", conflicts_with_all = ["squash", "amend"])]
+        fixup: Option<String>,
+
+        /// Make a "squash!" commit for COMMIT, to be folded into it later
+        #[arg(long, value_name = "COMMIT", conflicts_with = "amend")]
+        squash: Option<String>,
+
+        /// Record someone else as the author, as "Name <email>"
+        #[arg(long, value_name = "AUTHOR")]
+        author: Option<String>,
+
+        /// Record this as the author date, e.g. "2024-05-01 12:00 +0200" or "2 days ago"
+        #[arg(long)]
+        date: Option<String>,
+
+        /// Add a Signed-off-by trailer for the committer
+        #[arg(short, long)]
+        signoff: bool,
+
+        /// Add a trailer to the message, as 'key=value' or 'key: value'
+        #[arg(long = "trailer", value_name = "TRAILER")]
+        trailers: Vec<String>,
     },
 
     /// Displays the commit history of the current branch.
     ///
     /// Traverses the commit graph backwards from the current HEAD, showing the
     /// author, date, and message for each commit.
-    Log,
+    #[command(after_help = "EXAMPLES:\n    rit log --oneline --graph --all\n    rit log --pretty=format:\"%h %an %ar %s\" --date=relative\n    rit log -p -n 3\n    rit log -n 5 --first-parent\n    rit log --since=\"1 month ago\" -- src/api\n    rit log --author=alice main..feature\n    rit log --name-status -M\n    rit log --follow src/new_name.rs")]
+    Log {
+        /// Show each commit on a single line
+        #[arg(long)]
+        oneline: bool,
+
+        /// Limit the number of commits to show
+        #[arg(short = 'n', long = "max-count", value_name = "COUNT")]
+        max_count: Option<usize>,
+
+        /// Draw an ASCII graph of the branch and merge history
+        #[arg(long)]
+        graph: bool,
+
+        /// Show commits reachable from any branch or tag, not just HEAD
+        #[arg(long)]
+        all: bool,
+
+        /// Follow only the first parent of merge commits
+        #[arg(long)]
+        first_parent: bool,
+
+        #[command(flatten)]
+        format: FormatArgs,
+
+        /// Show each commit's patch against its first parent
+        #[arg(short = 'p', long = "patch")]
+        patch: bool,
+
+        /// Show a diffstat of each commit's changes
+        #[arg(long)]
+        stat: bool,
+
+        /// Only show commits whose author matches this regex
+        #[arg(long, value_name = "REGEX")]
+        author: Option<String>,
+
+        /// Only show commits whose message matches this regex
+        #[arg(long, value_name = "REGEX")]
+        grep: Option<String>,
+
+        /// Only show commits authored since this date (e.g. 2024-01-31, "2 weeks ago")
+        #[arg(long, alias = "after", value_name = "DATE", value_parser = parse_date)]
+        since: Option<i64>,
+
+        /// Only show commits authored until this date
+        #[arg(long, alias = "before", value_name = "DATE", value_parser = parse_date)]
+        until: Option<i64>,
+
+        /// Revisions or ranges to show (main, A..B, A...B, ^X); defaults to HEAD
+        #[arg(value_name = "REVISION")]
+        revisions: Vec<String>,
+
+        /// Only show commits touching these paths
+        #[arg(last = true, value_name = "PATH")]
+        paths: Vec<String>,
+
+        /// List the files changed by each commit along with their status
+        #[arg(long)]
+        name_status: bool,
+
+        /// Only show commits touching this file, following it across renames
+        #[arg(long, value_name = "PATH")]
+        follow: Option<String>,
+
+        #[command(flatten)]
+        renames: RenameArgs,
+    },
+
+    /// Shows a commit, a tree or a file as it was in some commit.
+    ///
+    /// For a commit, prints its header and its changes against its parent
+    /// (a combined diff for merges). `<rev>:<path>` prints a file's contents
+    /// or lists a directory, and a tree lists its entries.
+    #[command(after_help = "EXAMPLES:\n    rit show\n    rit show main --stat\n    rit show HEAD:src/main.rs\n    rit show --format=\"%h %s\" feature")]
+    Show {
+        /// The commit, tree or `<rev>:<path>` to show
+        #[arg(default_value = "HEAD")]
+        revision: String,
+
+        #[command(flatten)]
+        format: FormatArgs,
+
+        /// Show the commit on a single line
+        #[arg(long)]
+        oneline: bool,
+
+        /// Show a diffstat instead of the patch
+        #[arg(long)]
+        stat: bool,
+    },
 
     /// Lists the contents of a given tree object.
     ///
     /// A tree object represents a directory in the repository. This command will
     /// show the blobs (files) and other trees (subdirectories) it contains.
     LsTree {
-        /// The 40-character SHA-1 hash of the tree or a commit object
+        /// The tree or commit to list, e.g. a hash, a branch or HEAD~1
         hash: String,
     },
 
     /// Switches the current HEAD to a specified commit or branch.
     ///
     /// This command updates the files in your working directory to match the
-    /// version stored in the target commit or branch.
-    /// WARNING: This can overwrite uncommitted changes in your working directory.
+    /// version stored in the target commit or branch. Local changes to files
+    /// that are the same in both are carried over; if a changed file would be
+    /// overwritten, nothing is touched and the checkout is refused.
+    #[command(after_help = "EXAMPLES:\n    rit checkout main\n    rit checkout -\n    rit checkout HEAD~2\n    rit checkout -b feature\n    rit checkout -B hotfix v1.2.0")]
     Checkout {
-        /// The commit hash or branch name to switch to
-        target: String,
+        /// The branch to switch to, any revision (e.g. a short hash or HEAD~1)
+        /// to detach at, `-` for the previous branch, or the start point with -b/-B
+        target: Option<String>,
+
+        /// Create a new branch with this name and switch to it
+        #[arg(short = 'b', value_name = "NEW_BRANCH", conflicts_with_all = ["force_create", "detach"])]
+        new_branch: Option<String>,
+
+        /// Like -b, but reset the branch if it already exists
+        #[arg(short = 'B', value_name = "NEW_BRANCH", conflicts_with = "detach")]
+        force_create: Option<String>,
+
+        /// Detach HEAD at the target even if it is a branch
+        #[arg(long)]
+        detach: bool,
+    },
+
+    /// Switches to a branch.
+    ///
+    /// With -c a new branch is created at the start point (HEAD by default)
+    /// and switched to. Starting at HEAD keeps the working directory as it
+    /// is, which makes it the way to turn a detached HEAD into a branch.
+    /// Checking out a commit without a branch needs --detach.
+    #[command(after_help = "EXAMPLES:\n    rit switch main\n    rit switch -\n    rit switch -c feature\n    rit switch -C hotfix v1.2.0\n    rit switch --detach HEAD~2")]
+    Switch {
+        /// The branch to switch to, `-` for the previous branch, or the start
+        /// point with -c/-C/--detach
+        target: Option<String>,
+
+        /// Create a new branch with this name and switch to it
+        #[arg(short, long, value_name = "NEW_BRANCH", conflicts_with_all = ["force_create", "detach"])]
+        create: Option<String>,
+
+        /// Like -c, but reset the branch if it already exists
+        #[arg(short = 'C', long, value_name = "NEW_BRANCH", conflicts_with = "detach")]
+        force_create: Option<String>,
+
+        /// Detach HEAD at the given revision
+        #[arg(long)]
+        detach: bool,
     },
 
     /// Manages branches in the repository.
     ///
-    /// When run without arguments, it lists all local branches.
-    /// Use the '-c' or '--create' flag to create a new branch from the current HEAD.
+    /// When run without arguments, it lists all local branches, sorted by name.
+    /// With a name it creates a new branch at the given start point (HEAD by
+    /// default). Branches are only deleted with -d once they are merged into
+    /// HEAD; -D deletes them regardless.
+    #[command(after_help = "EXAMPLES:\n    rit branch feature\n    rit branch hotfix v1.2.0\n    rit branch -f feature HEAD~2\n    rit branch -m feature feature-old\n    rit branch -d feature\n    rit branch -v --no-merged")]
     Branch {
+        /// The branch to create and its start point, the branches to delete,
+        /// or the old and new names for -m
+        names: Vec<String>,
+
         /// Creates a new branch with the given name
-        #[arg(short, long)]
+        #[arg(short, long, conflicts_with_all = ["delete", "force_delete", "rename"])]
         create: Option<String>,
+
+        /// Delete branches that are fully merged into HEAD
+        #[arg(short, long, conflicts_with_all = ["rename", "force_delete"])]
+        delete: bool,
+
+        /// Delete branches even if they are not merged
+        #[arg(short = 'D', conflicts_with = "rename")]
+        force_delete: bool,
+
+        /// Rename a branch (the current one if only a new name is given)
+        #[arg(short = 'm', long = "move")]
+        rename: bool,
+
+        /// Reset an existing branch to the start point, or overwrite the
+        /// target of a rename
+        #[arg(short, long)]
+        force: bool,
+
+        /// Show the tip commit of each branch
+        #[arg(short, long)]
+        verbose: bool,
+
+        /// Only list branches merged into the commit (HEAD by default)
+        #[arg(long, value_name = "COMMIT", num_args = 0..=1, default_missing_value = "HEAD")]
+        merged: Option<String>,
+
+        /// Only list branches not merged into the commit (HEAD by default)
+        #[arg(long, value_name = "COMMIT", num_args = 0..=1, default_missing_value = "HEAD")]
+        no_merged: Option<String>,
+    },
+
+    /// Creates, lists or deletes tags.
+    ///
+    /// Without -a or -m a lightweight tag is created: a ref pointing straight at
+    /// the commit. With a message an annotated tag object is created that also
+    /// records the tagger and date. Tags can be used wherever a revision is
+    /// accepted.
+    #[command(after_help = "EXAMPLES:\n    rit tag v1.0.0\n    rit tag -a v1.2.0 -m \"Release 1.2.0\" main\n    rit tag -l 'v1.*'\n    rit tag -d v1.0.0")]
+    Tag {
+        /// The tag to create or delete, or a pattern with -l
+        name: Option<String>,
+
+        /// The commit (or other object) to tag; defaults to HEAD
+        target: Option<String>,
+
+        /// Create an annotated tag object
+        #[arg(short, long)]
+        annotate: bool,
+
+        /// The message of an annotated tag
+        #[arg(short, long)]
+        message: Option<String>,
+
+        /// Delete the tag
+        #[arg(short, long, conflicts_with_all = ["annotate", "message", "list"])]
+        delete: bool,
+
+        /// List tags, optionally only those matching the pattern
+        #[arg(short, long)]
+        list: bool,
+
+        /// Replace an existing tag
+        #[arg(short, long)]
+        force: bool,
+    },
+
+    /// Names a commit after the nearest tag it descends from.
+    ///
+    /// Prints the tag name, followed by the number of commits made since the
+    /// tag and the abbreviated commit hash, e.g. `v1.4.0-12-gabc1234`. A
+    /// commit that is tagged itself is described by the tag name alone.
+    /// Only annotated tags are used unless --tags is given.
+    #[command(after_help = "EXAMPLES:\n    rit describe\n    rit describe --tags --dirty\n    rit describe --long v1.2.0~3")]
+    Describe {
+        /// The commit to describe; defaults to HEAD
+        revision: Option<String>,
+
+        /// Use lightweight tags as well as annotated ones
+        #[arg(long)]
+        tags: bool,
+
+        /// Always show the commit count and hash, even on a tagged commit
+        #[arg(long)]
+        long: bool,
+
+        /// Append a mark (default "-dirty") if the working tree has changes
+        #[arg(long, value_name = "MARK", num_args = 0..=1, require_equals = true, default_missing_value = "-dirty")]
+        dirty: Option<String>,
+
+        /// Show the abbreviated hash when no tag describes the commit
+        #[arg(long)]
+        always: bool,
     },
 
     /// Shows the status of the working directory and the staging area.
     ///
     /// This command displays which files have been modified, which are staged
     /// for the next commit, and which files are new and untracked by Rit.
-    Status,
+    /// Renamed files among the staged changes are detected automatically.
+    Status {
+        #[command(flatten)]
+        renames: RenameArgs,
+    },
 
     /// Gets or sets user-specific configuration options, like name and email.
     ///
@@ -113,9 +407,75 @@ pub enum Commands {
     },
 
     /// Shows changes between the working tree and the index.
+    #[command(
+        after_help = "EXAMPLES:\n    rit diff --stat\n    rit diff --word-diff=color README.md\n    rit config diff.md.wordRegex \"[^[:space:],]+\""
+    )]
     Diff {
         /// The specific file to diff. If not provided, shows all changes.
         path: Option<String>,
+
+        /// Show a histogram of added/removed lines per file
+        #[arg(long)]
+        stat: bool,
+
+        /// Show the patch too when a summary such as --stat is requested
+        #[arg(short = 'p', long = "patch")]
+        patch: bool,
+
+        /// Show added and removed line counts as tab-separated columns
+        #[arg(long)]
+        numstat: bool,
+
+        /// Show only the total number of files and lines changed
+        #[arg(long)]
+        shortstat: bool,
+
+        /// Show only the names of changed files
+        #[arg(long)]
+        name_only: bool,
+
+        /// Show the names of changed files along with their status (A/M/D)
+        #[arg(long)]
+        name_status: bool,
+
+        /// Output a base85-encoded binary patch for binary files
+        #[arg(long)]
+        binary: bool,
+
+        /// Show a word-level diff, marking words as plain text, color or porcelain
+        #[arg(
+            long,
+            value_name = "MODE",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "plain"
+        )]
+        word_diff: Option<WordDiffMode>,
+
+        /// Regex that decides what a word is (overrides diff.wordRegex)
+        #[arg(long, value_name = "REGEX")]
+        word_diff_regex: Option<String>,
+
+        /// Same as --word-diff=color, optionally with a word regex
+        #[arg(
+            long,
+            value_name = "REGEX",
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = ""
+        )]
+        color_words: Option<String>,
+
+        /// Highlight blocks of lines that were moved rather than changed
+        #[arg(long)]
+        color_moved: bool,
+
+        /// Show changes staged in the index relative to HEAD
+        #[arg(long, alias = "staged")]
+        cached: bool,
+
+        #[command(flatten)]
+        renames: RenameArgs,
     },
 
     /// Merge Different Branches
@@ -123,4 +483,356 @@ pub enum Commands {
         /// The name of the branch to merge in
         branch: String,
     },
+
+    /// Shows changes using an external diff tool such as meld, kdiff3 or vimdiff.
+    ///
+    /// The tool comes from '--tool' or the 'diff.tool' config entry. Its command
+    /// line can be set with 'difftool.<tool>.cmd', using the placeholders
+    /// $LOCAL and $REMOTE.
+    #[command(after_help = "EXAMPLES:\n    rit config diff.tool meld\n    rit difftool src/main.rs\n    rit config difftool.code.cmd 'code --wait --diff \"$LOCAL\" \"$REMOTE\"'")]
+    Difftool {
+        /// The specific file to diff. If not provided, shows all changes.
+        path: Option<String>,
+
+        /// The tool to use instead of the configured one
+        #[arg(short, long)]
+        tool: Option<String>,
+
+        /// Compare the index against HEAD instead of the working tree
+        #[arg(long, alias = "staged")]
+        cached: bool,
+    },
+
+    /// Resolves merge conflicts using an external merge tool.
+    ///
+    /// For each conflicted file, the base, ours and theirs versions are written
+    /// to temporary files and the tool from 'merge.tool' is launched with
+    /// $BASE, $LOCAL, $REMOTE and $MERGED. When the tool exits successfully,
+    /// the merged file is staged and the conflict is marked as resolved.
+    #[command(after_help = "EXAMPLES:\n    rit config merge.tool kdiff3\n    rit mergetool\n    rit config mergetool.mytool.cmd 'mytool \"$BASE\" \"$LOCAL\" \"$REMOTE\" -o \"$MERGED\"'")]
+    Mergetool {
+        /// Only resolve these conflicted files
+        paths: Vec<String>,
+
+        /// The tool to use instead of the configured one
+        #[arg(short, long)]
+        tool: Option<String>,
+    },
+
+    /// Shows the type, size or contents of a stored object.
+    ///
+    /// Exactly one of -t, -s, -p or -e is needed, unless --batch is used to
+    /// read object names from stdin.
+    #[command(after_help = "EXAMPLES:\n    rit cat-file -t HEAD\n    rit cat-file -p HEAD:src/main.rs\n    echo HEAD | rit cat-file --batch")]
+    CatFile {
+        /// Show the object type
+        #[arg(short = 't', group = "mode")]
+        show_type: bool,
+
+        /// Show the object size in bytes
+        #[arg(short = 's', group = "mode")]
+        size: bool,
+
+        /// Pretty-print the object's contents
+        #[arg(short = 'p', group = "mode")]
+        pretty: bool,
+
+        /// Exit with status 0 if the object exists, 1 otherwise, printing nothing
+        #[arg(short = 'e', group = "mode")]
+        exists: bool,
+
+        /// Print the header and contents of every object named on stdin
+        #[arg(long, conflicts_with = "mode")]
+        batch: bool,
+
+        /// The object to inspect
+        object: Option<String>,
+    },
+
+    /// Computes the blob hash of files, optionally storing them as objects.
+    HashObject {
+        /// Write the blobs into the object database
+        #[arg(short)]
+        write: bool,
+
+        /// Read the contents from stdin
+        #[arg(long)]
+        stdin: bool,
+
+        /// The files to hash
+        files: Vec<String>,
+    },
+
+    /// Creates tree objects from the index and prints the root tree's hash.
+    WriteTree,
+
+    /// Creates a commit object for a tree and prints its hash.
+    ///
+    /// Neither HEAD nor any branch is moved; use update-ref for that. The
+    /// message is read from stdin when -m is not given.
+    #[command(after_help = "EXAMPLE:\n    rit commit-tree $(rit write-tree) -p HEAD -m \"message\"")]
+    CommitTree {
+        /// The tree the commit records
+        tree: String,
+
+        /// A parent commit; repeat for merges
+        #[arg(short = 'p', value_name = "PARENT")]
+        parents: Vec<String>,
+
+        /// The commit message
+        #[arg(short, long)]
+        message: Option<String>,
+    },
+
+    /// Safely points a ref at a new object.
+    ///
+    /// When <OLD> is given, the ref is only updated if it still holds that
+    /// value (use 40 zeros to require that the ref does not exist yet). With
+    /// -d the ref is deleted and the second argument is the expected old value.
+    #[command(after_help = "EXAMPLES:\n    rit update-ref refs/heads/main $NEW $OLD\n    rit update-ref -d refs/heads/topic")]
+    UpdateRef {
+        /// Delete the ref instead of updating it
+        #[arg(short)]
+        delete: bool,
+
+        /// The reason to record in the reflog
+        #[arg(short, conflicts_with = "delete")]
+        message: Option<String>,
+
+        /// The full ref name, e.g. refs/heads/main, or HEAD
+        refname: String,
+
+        /// The new value (or the expected old value with -d)
+        new_value: Option<String>,
+
+        /// The value the ref must currently have
+        old_value: Option<String>,
+    },
+
+    /// Resolves revisions to object hashes.
+    #[command(after_help = "EXAMPLES:\n    rit rev-parse HEAD\n    rit rev-parse --short main\n    rit rev-parse --abbrev-ref HEAD")]
+    RevParse {
+        /// Abbreviate hashes (to 7 characters unless a length is given)
+        #[arg(long, value_name = "LENGTH", num_args = 0..=1, require_equals = true, default_missing_value = "7")]
+        short: Option<usize>,
+
+        /// Fail unless exactly one revision is given and it resolves
+        #[arg(long)]
+        verify: bool,
+
+        /// Show the branch name a ref points at instead of its hash
+        #[arg(long)]
+        abbrev_ref: bool,
+
+        /// Show the root of the working tree
+        #[arg(long)]
+        show_toplevel: bool,
+
+        /// Show the path of the .rit directory
+        #[arg(long)]
+        git_dir: bool,
+
+        /// The revisions to resolve
+        revisions: Vec<String>,
+    },
+
+    /// Shows or edits the reflog, the record of where HEAD and each branch
+    /// have pointed.
+    ///
+    /// Every commit, checkout, merge, reset or branch update appends an entry,
+    /// so commits lost by a mistaken checkout or merge can be found again and
+    /// named as HEAD@{n}, main@{n} or main@{yesterday}.
+    #[command(
+        args_conflicts_with_subcommands = true,
+        after_help = "EXAMPLES:\n    rit reflog\n    rit reflog show main\n    rit reflog expire --expire='30 days ago' --all\n    rit reflog delete HEAD@{2}"
+    )]
+    Reflog {
+        #[command(subcommand)]
+        action: Option<ReflogAction>,
+
+        /// The ref whose log to show; defaults to HEAD
+        refname: Option<String>,
+
+        /// Show at most this many entries
+        #[arg(short = 'n', long)]
+        max_count: Option<usize>,
+    },
+
+    /// Reads, changes or deletes a symbolic ref.
+    ///
+    /// A symbolic ref holds the name of another ref instead of a hash, the way
+    /// HEAD names the current branch. With one argument the ref it resolves to
+    /// is printed; with two it is pointed at the given ref.
+    #[command(after_help = "EXAMPLES:\n    rit symbolic-ref HEAD\n    rit symbolic-ref --short HEAD\n    rit symbolic-ref HEAD refs/heads/main")]
+    SymbolicRef {
+        /// Print the short name, e.g. main instead of refs/heads/main
+        #[arg(long)]
+        short: bool,
+
+        /// Exit with status 1 instead of an error if the ref is not symbolic
+        #[arg(short, long)]
+        quiet: bool,
+
+        /// Delete the symbolic ref
+        #[arg(short, long, conflicts_with = "target")]
+        delete: bool,
+
+        /// The reason to record in the reflog when changing the target
+        #[arg(short, requires = "target")]
+        message: Option<String>,
+
+        /// The symbolic ref, e.g. HEAD
+        name: String,
+
+        /// The ref to point it at, e.g. refs/heads/main
+        target: Option<String>,
+    },
+
+    /// Packs refs into a single .rit/packed-refs file.
+    ///
+    /// With many branches and tags, one file per ref is slow to enumerate.
+    /// Tags are always packed, branches only with --all. Packed refs keep
+    /// working everywhere; updating one writes a loose file again.
+    #[command(after_help = "EXAMPLES:\n    rit pack-refs --all")]
+    PackRefs {
+        /// Pack branches as well as tags
+        #[arg(long)]
+        all: bool,
+
+        /// Keep the loose ref files after packing them
+        #[arg(long)]
+        no_prune: bool,
+    },
+
+    /// Checks that a name is a valid ref name, exiting with status 1 if not.
+    ///
+    /// Ref names may be split into components with '/', but no component may
+    /// start with '.' or end with '.lock', and names may not contain '..',
+    /// '@{', control characters, spaces or any of ~ ^ : ? * [ \.
+    #[command(after_help = "EXAMPLES:\n    rit check-ref-format refs/heads/feature/login\n    rit check-ref-format --branch feature/login")]
+    CheckRefFormat {
+        /// Check the name as a branch name and print it
+        #[arg(long)]
+        branch: bool,
+
+        /// Accept names with a single component, such as 'main'
+        #[arg(long)]
+        allow_onelevel: bool,
+
+        /// Remove a leading slash and repeated slashes, and print the result
+        #[arg(long)]
+        normalize: bool,
+
+        /// The name to check
+        name: String,
+    },
+
+    /// Adds or parses the trailers at the end of a commit message.
+    ///
+    /// Trailers are 'Key: value' lines in the last paragraph of a message,
+    /// such as 'Signed-off-by:' or 'Reviewed-by:'. The messages are read
+    /// from the given files, or standard input, and printed with the new
+    /// trailers added.
+    #[command(after_help = "EXAMPLES:\n    rit interpret-trailers --trailer \"Reviewed-by: Ann Dev <ann@example.com>\" msg.txt\n    rit interpret-trailers --in-place --trailer Closes=42 msg.txt\n    rit log -n 1 --format=%B | rit interpret-trailers --parse")]
+    InterpretTrailers {
+        /// The message files; standard input when none are given
+        files: Vec<String>,
+
+        /// A trailer to add, as 'key=value' or 'key: value'
+        #[arg(long = "trailer", value_name = "TRAILER", conflicts_with = "parse")]
+        trailers: Vec<String>,
+
+        /// What to do when a trailer with the same key is already there
+        #[arg(long, value_name = "ACTION", default_value = "addIfDifferentNeighbor")]
+        if_exists: InterpretTrailersIfExists,
+
+        /// Print only the existing trailers, one per line
+        #[arg(long)]
+        parse: bool,
+
+        /// Print only the trailers, after adding the new ones
+        #[arg(long)]
+        only_trailers: bool,
+
+        /// Rewrite the files instead of printing them
+        #[arg(long)]
+        in_place: bool,
+    },
+}
+
+/// What `rit reflog` does; showing the log is the default.
+#[derive(Subcommand, Debug)]
+pub enum ReflogAction {
+    /// Shows a reflog, newest entry first
+    Show {
+        /// The ref whose log to show; defaults to HEAD
+        refname: Option<String>,
+
+        /// Show at most this many entries
+        #[arg(short = 'n', long)]
+        max_count: Option<usize>,
+    },
+
+    /// Removes entries older than a date from reflogs
+    Expire {
+        /// Drop entries older than this ('90 days ago' by default); 'all'
+        /// drops every entry and 'never' keeps them all
+        #[arg(long, value_name = "TIME")]
+        expire: Option<String>,
+
+        /// Expire the reflogs of all refs
+        #[arg(long)]
+        all: bool,
+
+        /// The refs whose logs to expire
+        refs: Vec<String>,
+    },
+
+    /// Removes single entries such as HEAD@{2} from reflogs
+    Delete {
+        /// The entries to remove
+        entries: Vec<String>,
+    },
+}
+
+/// Options controlling how commit headers are shown, shared by log and show.
+#[derive(Args, Debug)]
+pub struct FormatArgs {
+    /// Layout of each commit: oneline, short, medium, full, fuller or format:<fmt>
+    #[arg(long, alias = "format", value_name = "FORMAT", value_parser = parse_pretty)]
+    pub pretty: Option<Pretty>,
+
+    /// How dates are shown
+    #[arg(long, value_enum, value_name = "MODE")]
+    pub date: Option<DateMode>,
+
+    /// Show abbreviated commit hashes
+    #[arg(long)]
+    pub abbrev_commit: bool,
+}
+
+/// Rename and copy detection flags shared by `diff`, `status` and `log`.
+#[derive(Args, Debug)]
+pub struct RenameArgs {
+    /// Detect renames; optionally the minimum similarity, attached as in -M75%
+    #[arg(
+        short = 'M',
+        long = "find-renames",
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "50%",
+        value_parser = parse_similarity
+    )]
+    pub find_renames: Option<u8>,
+
+    /// Detect copies as well as renames; optionally the minimum similarity (-C75%)
+    #[arg(
+        short = 'C',
+        long = "find-copies",
+        value_name = "N",
+        num_args = 0..=1,
+        default_missing_value = "50%",
+        value_parser = parse_similarity
+    )]
+    pub find_copies: Option<u8>,
 }
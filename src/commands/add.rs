@@ -1,5 +1,7 @@
-use super::utils::{find_repo_root, is_ignored, load_ritignore, write_object};
-use sha1::{Digest, Sha1};
+use super::merge::mark_resolved;
+use super::utils::{
+    blob_object, find_repo_root, is_ignored, load_ritignore, read_worktree_file, trust_file_mode, write_object, MODE_SYMLINK,
+};
 use std::fs::{self};
 use std::io::{self};
 use std::path::{Path, PathBuf};
@@ -12,9 +14,11 @@ pub fn run(path: &str) -> io::Result<()> {
     // Load ignore patterns
     let ignore_patterns = load_ritignore(&repo_path)?;
     
-    if path.is_file() {
+    // Symlinks are added as links, never followed
+    let metadata = fs::symlink_metadata(&path);
+    if metadata.as_ref().is_ok_and(|m| !m.is_dir()) {
         add_file(repo_root, &repo_path, &path, &ignore_patterns)?;
-    } else if path.is_dir() {
+    } else if metadata.is_ok() {
         add_dir(repo_root, &repo_path, &path, &ignore_patterns)?;
     } else {
         eprintln!("Path '{}' does not exist", path.display());
@@ -45,10 +49,11 @@ fn add_dir(
             continue;
         }
         
-        if path.is_file() {
-            add_file(repo_root, repo_path, &path, ignores)?;
-        } else if path.is_dir() {
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
             add_dir(repo_root, repo_path, &path, ignores)?;
+        } else if file_type.is_file() || file_type.is_symlink() {
+            add_file(repo_root, repo_path, &path, ignores)?;
         }
     }
     
@@ -67,18 +72,10 @@ fn add_file(
         return Ok(());
     }
     
-    let contents = fs::read(file_path)?;
-    let blob_header = format!("blob {}\0", contents.len());
-    let mut blob_data = Vec::new();
-    blob_data.extend_from_slice(blob_header.as_bytes());
-    blob_data.extend_from_slice(&contents);
-    
-    let mut hasher = Sha1::new();
-    hasher.update(&blob_data);
-    let hash = hex::encode(hasher.finalize());
-    
-    // Get file mode
-    let mode = get_file_mode(file_path)?;
+    let Some((mut mode, contents)) = read_worktree_file(file_path)? else {
+        return Ok(());
+    };
+    let (hash, blob_data) = blob_object(&contents);
     
     // Check if file is already in index with same hash
     let relative_path = file_path.strip_prefix(repo_root).unwrap_or(file_path);
@@ -88,12 +85,17 @@ fn add_file(
     // Read current index to check if update is needed
     let index_path = repo_path.join("index");
     let mut needs_update = true;
+    let keep_mode = !trust_file_mode(repo_path)?;
     
     if index_path.exists() {
         let content = fs::read_to_string(&index_path)?;
         for line in content.lines() {
             let parts: Vec<&str> = line.splitn(3, ' ').collect();
             if parts.len() == 3 && parts[2] == normalized_path {
+                // Without core.fileMode a file keeps the executable bit it was staged with
+                if keep_mode && mode != MODE_SYMLINK && parts[0] != MODE_SYMLINK {
+                    mode = parts[0].to_string();
+                }
                 // File is already in index - check if hash changed
                 if parts[1] == hash && parts[0] == mode {
                     needs_update = false;
@@ -103,6 +105,9 @@ fn add_file(
         }
     }
     
+    // Staging a conflicted file marks it as resolved
+    mark_resolved(repo_path, Path::new(normalized_path))?;
+
     if !needs_update {
         // File unchanged, skip
         return Ok(());
@@ -119,30 +124,6 @@ fn add_file(
     Ok(())
 }
 
-/// Get file mode (permissions)
-fn get_file_mode(file_path: &Path) -> io::Result<String> {
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::PermissionsExt;
-        let metadata = fs::metadata(file_path)?;
-        let permissions = metadata.permissions();
-        let mode = permissions.mode();
-        
-        // Check if executable
-        if mode & 0o111 != 0 {
-            Ok("100755".to_string()) // Executable file
-        } else {
-            Ok("100644".to_string()) // Regular file
-        }
-    }
-    
-    #[cfg(not(unix))]
-    {
-        // On Windows, default to regular file
-        Ok("100644".to_string())
-    }
-}
-
 /// Append <mode> <sha1> <filename> to index
 fn update_index(
     repo_root: &Path,
@@ -1,79 +1,182 @@
-use super::utils::{find_repo_root, read_head_commit};
-use std::fs;
-use std::io::{self, Write};
-
-pub fn run(branch_name: Option<&str>, is_creating: bool) -> io::Result<()> {
-    if is_creating {
-        if let Some(name) = branch_name {
-            create_branch(name)?;
-        } else {
-            eprintln!("Error: branch name required for creation");
+use super::commit::read_commit;
+use super::merge::ancestors;
+use super::reflog::rename_reflog;
+use super::revision::resolve_commit;
+use super::utils::{
+    check_short_ref_name, delete_ref, find_repo_root, get_current_branch, list_refs, read_head_commit, read_ref,
+    ref_exists, update_ref, write_symbolic_ref,
+};
+use std::io;
+use std::path::Path;
+
+/// Options for `rit branch`.
+#[derive(Debug, Default)]
+pub struct BranchOptions {
+    /// Positional arguments: the branch (and start point) to create, the
+    /// branches to delete, or the old and new names for `-m`
+    pub names: Vec<String>,
+    /// Delete the named branches, refusing unmerged ones
+    pub delete: bool,
+    /// Delete the named branches even if they are not merged
+    pub force_delete: bool,
+    /// Rename a branch
+    pub rename: bool,
+    /// Reset an existing branch to the start point, or overwrite on rename
+    pub force: bool,
+    /// Show the tip hash and subject of each branch
+    pub verbose: bool,
+    /// Only list branches whose tips are reachable from this commit
+    pub merged: Option<String>,
+    /// Only list branches whose tips are not reachable from this commit
+    pub no_merged: Option<String>,
+}
+
+pub fn run(options: &BranchOptions) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    let names: Vec<&str> = options.names.iter().map(String::as_str).collect();
+
+    if options.delete || options.force_delete {
+        if names.is_empty() {
+            return Err(io::Error::other("branch name required"));
         }
+        return names.iter().try_for_each(|name| delete_branch(&repo_path, name, options.force_delete));
+    }
+    if options.rename {
+        return match names[..] {
+            [new] => {
+                let current = get_current_branch()?.ok_or_else(|| io::Error::other("no branch to rename (HEAD is detached)"))?;
+                rename_branch(&repo_path, &current, new, options.force)
+            }
+            [old, new] => rename_branch(&repo_path, old, new, options.force),
+            _ => Err(io::Error::other("usage: rit branch -m [<old>] <new>")),
+        };
+    }
+    match names[..] {
+        [] => list_branches(&repo_path, options),
+        [name] => create_branch(&repo_path, name, "HEAD", options.force),
+        [name, start] => create_branch(&repo_path, name, start, options.force),
+        _ => Err(io::Error::other("too many arguments for creating a branch")),
+    }
+}
+
+fn create_branch(repo_path: &Path, name: &str, start: &str, force: bool) -> io::Result<()> {
+    check_short_ref_name(name, "branch")?;
+    let refname = format!("refs/heads/{}", name);
+    let exists = ref_exists(repo_path, &refname)?;
+    if exists && !force {
+        return Err(io::Error::other(format!("a branch named '{}' already exists", name)));
+    }
+    if exists && get_current_branch()?.as_deref() == Some(name) {
+        return Err(io::Error::other(format!("cannot force update the current branch '{}'", name)));
+    }
+
+    let commit = resolve_commit(repo_path, start)
+        .map_err(|_| io::Error::other(format!("not a valid object name: '{}'", start)))?;
+    let reason = match exists {
+        true => format!("branch: Reset to {}", start),
+        false => format!("branch: Created from {}", start),
+    };
+    update_ref(repo_path, &refname, &commit, (!force).then_some(""), &reason)?;
+
+    if exists {
+        println!("Reset branch '{}' to {}", name, &commit[..7]);
     } else {
-        show_branch()?;
+        println!("Branch '{}' created!", name);
     }
     Ok(())
 }
 
-fn create_branch(branch_name: &str) -> io::Result<()> {
-    let current_repo_path = find_repo_root()?;
-    let current_commit_hash = read_head_commit(&current_repo_path)?;
+fn delete_branch(repo_path: &Path, name: &str, force: bool) -> io::Result<()> {
+    check_short_ref_name(name, "branch")?;
+    let refname = format!("refs/heads/{}", name);
+    let Some(tip) = read_ref(repo_path, &refname)? else {
+        return Err(io::Error::other(format!("branch '{}' not found.", name)));
+    };
+    if get_current_branch()?.as_deref() == Some(name) {
+        return Err(io::Error::other(format!("Cannot delete branch '{}' checked out", name)));
+    }
+
+    if !force {
+        // Merged means the tip is reachable from HEAD, so nothing is lost
+        let merged = match read_head_commit(repo_path)? {
+            Some(head) => ancestors(repo_path, &head)?.contains(&tip),
+            None => false,
+        };
+        if !merged {
+            return Err(io::Error::other(format!(
+                "The branch '{}' is not fully merged.\nIf you are sure you want to delete it, run 'rit branch -D {}'.",
+                name, name
+            )));
+        }
+    }
 
-    let branch_path = current_repo_path
-        .join("refs")
-        .join("heads")
-        .join(branch_name);
+    delete_ref(repo_path, &refname, Some(&tip))?;
+    println!("Deleted branch {} (was {}).", name, &tip[..7]);
+    Ok(())
+}
 
-    if branch_path.exists() {
-        println!("Branch '{}' already exists!", branch_name);
+fn rename_branch(repo_path: &Path, old: &str, new: &str, force: bool) -> io::Result<()> {
+    check_short_ref_name(old, "branch")?;
+    let old_ref = format!("refs/heads/{}", old);
+    let new_ref = format!("refs/heads/{}", new);
+    let Some(tip) = read_ref(repo_path, &old_ref)? else {
+        return Err(io::Error::other(format!("branch '{}' not found.", old)));
+    };
+    if old == new {
         return Ok(());
     }
+    check_short_ref_name(new, "branch")?;
+    if ref_exists(repo_path, &new_ref)? && !force {
+        return Err(io::Error::other(format!("a branch named '{}' already exists", new)));
+    }
 
-    let mut file = fs::OpenOptions::new()
-        .write(true)
-        .create_new(true)
-        .open(branch_path)?;
+    // The history of the branch moves with it
+    rename_reflog(repo_path, &old_ref, &new_ref)?;
+    let reason = format!("Branch: renamed {} to {}", old_ref, new_ref);
+    update_ref(repo_path, &new_ref, &tip, (!force).then_some(""), &reason)?;
+    delete_ref(repo_path, &old_ref, Some(&tip))?;
 
-    if let Some(hash) = current_commit_hash {
-        file.write_all(hash.as_bytes())?;
+    // Keep HEAD attached when renaming the branch we are on
+    if get_current_branch()?.as_deref() == Some(old) {
+        write_symbolic_ref(repo_path, "HEAD", &new_ref)?;
     }
-
-    println!("Branch '{}' created!", branch_name);
+    println!("Branch '{}' renamed to '{}'", old, new);
     Ok(())
 }
 
-fn show_branch() -> io::Result<()> {
-    let repo_path = find_repo_root()?;
-    let heads_dir = repo_path.join("refs").join("heads");
+fn list_branches(repo_path: &Path, options: &BranchOptions) -> io::Result<()> {
+    let current_branch = get_current_branch()?;
+    let mut branches = list_refs(repo_path, "heads")?;
 
-    if !heads_dir.exists() {
-        println!("No branches found");
-        return Ok(());
+    if let Some(commit) = &options.merged {
+        let reachable = ancestors(repo_path, &resolve_commit(repo_path, commit)?)?;
+        branches.retain(|(_, tip)| reachable.contains(tip));
+    }
+    if let Some(commit) = &options.no_merged {
+        let reachable = ancestors(repo_path, &resolve_commit(repo_path, commit)?)?;
+        branches.retain(|(_, tip)| !reachable.contains(tip));
     }
 
-    // Read HEAD to see which branch we’re on
-    let head_path = repo_path.join("HEAD");
-    let head_content = fs::read_to_string(head_path).unwrap_or_default();
-    let current_branch = if head_content.starts_with("ref: ") {
-        Some(
-            head_content
-                .trim_start_matches("ref: refs/heads/")
-                .trim()
-                .to_string(),
-        )
-    } else {
-        None
-    };
+    // A detached HEAD is listed first, like a branch of its own
+    let mut rows: Vec<(bool, String, String)> = Vec::new();
+    if current_branch.is_none()
+        && let Some(head) = read_head_commit(repo_path)?
+    {
+        rows.push((true, format!("(HEAD detached at {})", &head[..7]), head));
+    }
+    for (name, tip) in branches {
+        rows.push((current_branch.as_ref() == Some(&name), name, tip));
+    }
 
-    for entry in fs::read_dir(&heads_dir)? {
-        let entry = entry?;
-        let branch_name = entry.file_name().into_string().unwrap();
-        if Some(&branch_name) == current_branch.as_ref() {
-            println!("* {}", branch_name);
+    let width = rows.iter().map(|(_, name, _)| name.len()).max().unwrap_or(0);
+    for (is_current, name, tip) in rows {
+        let marker = if is_current { '*' } else { ' ' };
+        if options.verbose {
+            let summary = read_commit(repo_path, &tip)?.summary().to_string();
+            println!("{} {:<width$} {} {}", marker, name, &tip[..7], summary, width = width);
         } else {
-            println!("  {}", branch_name);
+            println!("{} {}", marker, name);
         }
     }
-
     Ok(())
 }
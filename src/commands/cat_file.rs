@@ -0,0 +1,82 @@
+use super::revision::resolve_revision;
+use super::utils::{find_repo_root, object_type, read_object, read_tree};
+use std::io::{self, BufRead, Write};
+use std::path::Path;
+
+/// What `rit cat-file` reports about an object.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CatMode {
+    /// `-t`: the object type
+    Type,
+    /// `-s`: the content size in bytes
+    Size,
+    /// `-p`: the content, with trees shown as readable listings
+    Pretty,
+    /// `-e`: only the exit status, telling whether the object exists
+    Exists,
+}
+
+pub fn run(object: Option<&str>, mode: Option<CatMode>, batch: bool) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    if batch {
+        return run_batch(&repo_path);
+    }
+
+    let (Some(object), Some(mode)) = (object, mode) else {
+        return Err(io::Error::other("cat-file needs one of -t, -s, -p or -e and an object, or --batch"));
+    };
+    let hash = match resolve_revision(&repo_path, object) {
+        Ok(hash) if object_exists(&repo_path, &hash) => hash,
+        // -e reports a missing object through the exit status alone
+        _ if mode == CatMode::Exists => std::process::exit(1),
+        Ok(_) => return Err(io::Error::other(format!("Not a valid object name {}", object))),
+        Err(e) => return Err(e),
+    };
+
+    match mode {
+        CatMode::Type => println!("{}", object_type(&repo_path, &hash)?),
+        CatMode::Size => println!("{}", read_object(&repo_path, &hash)?.len()),
+        CatMode::Pretty => print_pretty(&repo_path, &hash)?,
+        CatMode::Exists => {}
+    }
+    Ok(())
+}
+
+/// Reads object names from stdin and prints `<hash> <type> <size>` followed
+/// by the raw content for each one, or `<name> missing`.
+fn run_batch(repo_path: &Path) -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut out = io::stdout().lock();
+    for line in stdin.lock().lines() {
+        let line = line?;
+        let name = line.trim();
+        if name.is_empty() {
+            continue;
+        }
+        match resolve_revision(repo_path, name) {
+            Ok(hash) if object_exists(repo_path, &hash) => {
+                let content = read_object(repo_path, &hash)?;
+                writeln!(out, "{} {} {}", hash, object_type(repo_path, &hash)?, content.len())?;
+                out.write_all(&content)?;
+                writeln!(out)?;
+            }
+            _ => writeln!(out, "{} missing", name)?,
+        }
+    }
+    Ok(())
+}
+
+fn print_pretty(repo_path: &Path, hash: &str) -> io::Result<()> {
+    if object_type(repo_path, hash)? != "tree" {
+        return io::stdout().write_all(&read_object(repo_path, hash)?);
+    }
+    for (mode, name, entry_hash) in read_tree(repo_path, hash)? {
+        let kind = if mode == "40000" { "tree" } else { "blob" };
+        println!("{:0>6} {} {}\t{}", mode, kind, entry_hash, name);
+    }
+    Ok(())
+}
+
+fn object_exists(repo_path: &Path, hash: &str) -> bool {
+    hash.len() == 40 && repo_path.join("objects").join(&hash[..2]).join(&hash[2..]).is_file()
+}
@@ -0,0 +1,26 @@
+use super::utils::{check_ref_format, check_short_ref_name};
+use std::io;
+
+/// Checks whether a name is acceptable as a ref, exiting with status 1 if
+/// not. With `branch` the name is checked as a branch name and printed.
+pub fn run(name: &str, branch: bool, allow_onelevel: bool, normalize: bool) -> io::Result<()> {
+    if branch {
+        check_short_ref_name(name, "branch")?;
+        println!("{}", name);
+        return Ok(());
+    }
+
+    // --normalize drops a leading slash and collapses repeated ones first
+    let normalized = match normalize {
+        true => name.split('/').filter(|c| !c.is_empty()).collect::<Vec<_>>().join("/"),
+        false => name.to_string(),
+    };
+    let valid = check_ref_format(&normalized).is_ok() && (allow_onelevel || normalized.contains('/'));
+    if !valid {
+        std::process::exit(1);
+    }
+    if normalize {
+        println!("{}", normalized);
+    }
+    Ok(())
+}
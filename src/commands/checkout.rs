@@ -1,134 +1,370 @@
-use super::commit::read_index;
-use super::utils::{find_repo_root, read_object};
-use sha1::{Digest, Sha1};
-use std::fs::{self, File};
-use std::io::{self, Write};
-use std::path::Path;
-
-pub fn run(target: &str) -> io::Result<()> {
+use super::commit::{read_commit, read_index, write_index, IndexEntry};
+use super::diff::FileMap;
+use super::log::walk_commits;
+use super::merge::ancestors;
+use super::reflog::{append_reflog, previous_branch};
+use super::revision::{peel, resolve_commit};
+use super::utils::{
+    blob_object, check_ref_format, check_short_ref_name, delete_ref, find_repo_root, get_current_branch,
+    get_tree_hash, list_refs, read_head_commit, read_object, read_ref, read_tree_entries, read_worktree_file,
+    ref_exists, remove_worktree_file, update_ref, write_locked, write_symbolic_ref, write_worktree_file,
+};
+use std::collections::BTreeSet;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Options for `rit checkout`.
+#[derive(Debug, Default)]
+pub struct CheckoutOptions {
+    /// `-b`/`-B`: create this branch at the target and switch to it
+    pub new_branch: Option<String>,
+    /// `-B`: reset the branch if it already exists
+    pub force_create: bool,
+    /// Detach HEAD even if the target is a branch
+    pub detach: bool,
+}
+
+pub fn run(target: Option<&str>, options: &CheckoutOptions) -> io::Result<()> {
     let repo_path = find_repo_root()?;
-    let work_dir = repo_path.parent().unwrap();
+    if let Some(name) = &options.new_branch {
+        return create_and_switch(&repo_path, name, target, options.force_create);
+    }
+
+    let target = match target {
+        Some("-") => previous_branch(&repo_path, 1)?,
+        Some(target) => target.to_string(),
+        None if options.detach => "HEAD".to_string(),
+        None => return Err(io::Error::other("missing branch or commit to check out")),
+    };
+    if !options.detach && is_branch(&repo_path, &target)? {
+        return switch_branch(&repo_path, &target);
+    }
+    // Anything else is a revision to detach HEAD at, e.g. a short hash or HEAD~2
+    detach_at(&repo_path, &target)
+}
 
-    // --- START: SAFETY CHECK ---
-    let index_entries = read_index(&repo_path)?;
-    let mut modified_files = Vec::new();
+fn is_branch(repo_path: &Path, name: &str) -> io::Result<bool> {
+    let refname = format!("refs/heads/{}", name);
+    Ok(check_ref_format(&refname).is_ok() && ref_exists(repo_path, &refname)?)
+}
 
-    for entry in &index_entries {
-        let file_path = work_dir.join(&entry.path);
-        if !file_path.exists() {
-            continue;
-        }
+/// Where HEAD was before a switch, for the reflog and the detached HEAD
+/// warning.
+pub(crate) struct HeadState {
+    commit: Option<String>,
+    /// The branch name, or the commit when detached
+    name: String,
+    detached: bool,
+}
 
-        let contents = fs::read(&file_path)?;
-        let blob_header = format!("blob {}\0", contents.len());
-        let mut blob_data = Vec::new();
-        blob_data.extend_from_slice(blob_header.as_bytes());
-        blob_data.extend_from_slice(&contents);
+impl HeadState {
+    pub fn read(repo_path: &Path) -> io::Result<HeadState> {
+        let commit = read_head_commit(repo_path)?;
+        let branch = get_current_branch()?;
+        Ok(HeadState {
+            name: branch.clone().or_else(|| commit.clone()).unwrap_or_default(),
+            detached: branch.is_none(),
+            commit,
+        })
+    }
 
-        let mut hasher = Sha1::new();
-        hasher.update(&blob_data);
-        let current_hash = hex::encode(hasher.finalize());
+    /// The tree HEAD pointed at, if it had a commit.
+    pub fn tree(&self, repo_path: &Path) -> io::Result<Option<String>> {
+        self.commit.as_deref().map(|commit| get_tree_hash(repo_path, commit)).transpose()
+    }
 
-        if current_hash != entry.sha1 {
-            modified_files.push(entry.path.display().to_string());
+    /// Points HEAD at `branch`, or at `commit` directly when `branch` is
+    /// `None`, and records the move in HEAD's reflog under `label`.
+    pub fn finish(&self, repo_path: &Path, commit: &str, branch: Option<&str>, label: &str) -> io::Result<()> {
+        if self.detached
+            && let Some(old) = &self.commit
+        {
+            warn_leaving_detached(repo_path, old, commit)?;
         }
+        match branch {
+            Some(branch) => write_symbolic_ref(repo_path, "HEAD", &format!("refs/heads/{}", branch))?,
+            None => write_locked(&repo_path.join("HEAD"), commit.as_bytes())?,
+        }
+        let old = self.commit.as_deref().unwrap_or_default();
+        append_reflog(repo_path, "HEAD", old, commit, &format!("checkout: moving from {} to {}", self.name, label))
     }
+}
 
-    if !modified_files.is_empty() {
-        let error_message = format!(
-            "error: Your local changes to the following files would be overwritten by checkout:\n  {}\n\nPlease commit your changes or stash them before you switch branches.",
-            modified_files.join("\n  ")
-        );
-        return Err(io::Error::other(error_message));
+/// `rit checkout <branch>` / `rit switch <branch>`.
+pub(crate) fn switch_branch(repo_path: &Path, name: &str) -> io::Result<()> {
+    if get_current_branch()?.as_deref() == Some(name) {
+        println!("Already on '{}'", name);
+        return Ok(());
     }
-    // --- END: SAFETY CHECK ---
+    let tip = read_ref(repo_path, &format!("refs/heads/{}", name))?
+        .ok_or_else(|| io::Error::other(format!("invalid reference: {}", name)))?;
 
-    let commit_hash: String;
+    let head = HeadState::read(repo_path)?;
+    let carried = switch_tree(repo_path, head.tree(repo_path)?.as_deref(), &get_tree_hash(repo_path, &tip)?, TreeOperation::Checkout)?;
+    head.finish(repo_path, &tip, Some(name), name)?;
+    print_carried(&carried);
+    println!("Switched to branch '{}'", name);
+    Ok(())
+}
 
-    let branch_path = repo_path.join("refs").join("heads").join(target);
-    if branch_path.exists() {
-        commit_hash = fs::read_to_string(&branch_path)?.trim().to_string();
-        fs::write(
-            repo_path.join("HEAD"),
-            format!("ref: refs/heads/{}", target),
-        )?;
-        println!("Switched to branch '{}'", target);
-    } else {
-        commit_hash = target.to_string();
-        fs::write(repo_path.join("HEAD"), &commit_hash)?;
-        println!("Note: HEAD is now at {}", &commit_hash[..7]);
-    }
+/// `rit checkout <commit>` / `rit switch --detach <rev>`.
+pub(crate) fn detach_at(repo_path: &Path, spec: &str) -> io::Result<()> {
+    let commit = resolve_commit(repo_path, spec)?;
+    let head = HeadState::read(repo_path)?;
+    let carried = switch_tree(repo_path, head.tree(repo_path)?.as_deref(), &get_tree_hash(repo_path, &commit)?, TreeOperation::Checkout)?;
+    head.finish(repo_path, &commit, None, spec)?;
+    print_carried(&carried);
+    let summary = read_commit(repo_path, &commit)?.summary().to_string();
+    println!("Note: HEAD is now at {} {}", &commit[..7], summary);
+    Ok(())
+}
 
-    let tree_hash = get_tree_hash(&repo_path, &commit_hash)?;
+/// `rit checkout -b/-B` / `rit switch -c/-C`: creates (or with `force`
+/// resets) a branch at `start` and switches to it.
+pub(crate) fn create_and_switch(repo_path: &Path, name: &str, start: Option<&str>, force: bool) -> io::Result<()> {
+    check_short_ref_name(name, "branch")?;
+    let refname = format!("refs/heads/{}", name);
+    let exists = ref_exists(repo_path, &refname)?;
+    if exists && !force {
+        return Err(io::Error::other(format!("a branch named '{}' already exists", name)));
+    }
 
-    // Clear the working directory BEFORE restoring files
-    clear_working_directory(work_dir)?;
+    let head = HeadState::read(repo_path)?;
+    let start_name = start.unwrap_or("HEAD");
+    let Some(commit) = (match (start, &head.commit) {
+        // On an unborn branch there is nothing to start from yet
+        (None, None) => None,
+        _ => Some(resolve_commit(repo_path, start_name)?),
+    }) else {
+        write_symbolic_ref(repo_path, "HEAD", &refname)?;
+        println!("Switched to a new branch '{}'", name);
+        return Ok(());
+    };
 
-    // Restore the working directory from the new tree
-    restore_tree(&repo_path, &tree_hash, work_dir)?;
+    // Without `force` the branch must not exist yet, so it is created before
+    // the files move: losing a race for the name leaves the working tree
+    // alone, and a refused switch takes the branch back
+    let reason = match exists {
+        true => format!("branch: Reset to {}", start_name),
+        false => format!("branch: Created from {}", start_name),
+    };
+    if !force {
+        update_ref(repo_path, &refname, &commit, Some(""), &reason)?;
+    }
+    // Starting at HEAD leaves the files alone, which is how a detached HEAD
+    // becomes a branch
+    let old_tree = head.tree(repo_path)?;
+    let carried = match switch_tree(repo_path, old_tree.as_deref(), &get_tree_hash(repo_path, &commit)?, TreeOperation::Checkout) {
+        Ok(carried) => carried,
+        Err(e) => {
+            if !force {
+                delete_ref(repo_path, &refname, Some(&commit))?;
+            }
+            return Err(e);
+        }
+    };
+    if force {
+        update_ref(repo_path, &refname, &commit, None, &reason)?;
+    }
+    head.finish(repo_path, &commit, Some(name), name)?;
 
+    print_carried(&carried);
+    match exists {
+        true => println!("Switched to and reset branch '{}'", name),
+        false => println!("Switched to a new branch '{}'", name),
+    }
     Ok(())
 }
 
-fn get_tree_hash(repo_path: &Path, hash: &str) -> io::Result<String> {
-    let commit_data = read_object(repo_path, hash)?;
-    let content = String::from_utf8_lossy(&commit_data);
-    content
-        .lines()
-        .find(|line| line.starts_with("tree "))
-        .map(|line| line[5..].to_string())
-        .ok_or_else(|| io::Error::other("Commit missing tree hash"))
+/// Lists the local changes that were carried over to the new HEAD.
+fn print_carried(carried: &[(char, PathBuf)]) {
+    for (status, path) in carried {
+        println!("{}\t{}", status, path.display());
+    }
+}
+
+/// The command moving the working tree, named in `switch_tree`'s errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TreeOperation {
+    Checkout,
+    Merge,
+}
+
+impl TreeOperation {
+    fn name(self) -> &'static str {
+        match self {
+            TreeOperation::Checkout => "checkout",
+            TreeOperation::Merge => "merge",
+        }
+    }
+
+    /// What the user should do their tidying up before.
+    fn action(self) -> &'static str {
+        match self {
+            TreeOperation::Checkout => "switch branches",
+            TreeOperation::Merge => "merge",
+        }
+    }
 }
 
-fn clear_working_directory(work_dir: &Path) -> io::Result<()> {
-    for entry in fs::read_dir(work_dir)? {
-        let entry = entry?;
-        let path = entry.path();
-        let file_name = path.file_name().unwrap_or_default();
+/// Moves the index and working directory from `old_tree` to `new_tree`.
+///
+/// Only files that differ between the two trees are touched, so local
+/// changes to other files are carried over. Before anything is written,
+/// the switch is refused if it would overwrite a file with staged or
+/// unstaged changes, or an untracked file, with advice that depends on
+/// `operation`. Returns the local changes that remain, as `M`/`A`/`D` with
+/// their paths.
+pub(crate) fn switch_tree(
+    repo_path: &Path,
+    old_tree: Option<&str>,
+    new_tree: &str,
+    operation: TreeOperation,
+) -> io::Result<Vec<(char, PathBuf)>> {
+    let work_dir = repo_path.parent().unwrap();
+    let old: FileMap = match old_tree {
+        Some(tree) => read_tree_entries(repo_path, tree)?,
+        None => FileMap::new(),
+    };
+    let new = read_tree_entries(repo_path, new_tree)?;
+    let mut index: FileMap = read_index(repo_path)?
+        .into_iter()
+        .map(|entry| (entry.path, (entry.mode, entry.sha1)))
+        .collect();
+    let worktree_hash = |path: &Path| -> io::Result<Option<String>> {
+        Ok(read_worktree_file(&work_dir.join(path))?.map(|(_, contents)| blob_object(&contents).0))
+    };
 
-        if file_name == ".rit" || file_name == ".git" {
+    // 1. Work out which paths change, and whether that would lose anything
+    let paths: BTreeSet<&PathBuf> = old.keys().chain(new.keys()).collect();
+    let mut updates = Vec::new();
+    let mut overwritten = Vec::new();
+    let mut untracked = Vec::new();
+    for path in paths {
+        let (before, after) = (old.get(path), new.get(path));
+        let staged = index.get(path);
+        // Unchanged between the commits, or already staged as in the target
+        if before == after || staged == after {
             continue;
         }
+        let on_disk = worktree_hash(path)?;
+        match staged {
+            None if before.is_none() => {
+                if on_disk.is_some() && on_disk.as_ref() != after.map(|(_, sha)| sha) {
+                    untracked.push(path.display().to_string());
+                }
+            }
+            _ => {
+                let unchanged = on_disk.as_ref() == staged.map(|(_, sha)| sha) || (after.is_none() && on_disk.is_none());
+                if staged != before || !unchanged {
+                    overwritten.push(path.display().to_string());
+                }
+            }
+        }
+        updates.push((path.clone(), after.cloned()));
+    }
+    if !overwritten.is_empty() {
+        return Err(io::Error::other(format!(
+            "error: Your local changes to the following files would be overwritten by {}:\n  {}\n\nPlease commit your changes or stash them before you {}.",
+            operation.name(),
+            overwritten.join("\n  "),
+            operation.action()
+        )));
+    }
+    if !untracked.is_empty() {
+        return Err(io::Error::other(format!(
+            "error: The following untracked working tree files would be overwritten by {}:\n  {}\n\nPlease move or remove them before you {}.",
+            operation.name(),
+            untracked.join("\n  "),
+            operation.action()
+        )));
+    }
+
+    // 2. Apply: removals first, so a file can make way for a directory
+    for (path, _) in updates.iter().filter(|(_, entry)| entry.is_none()) {
+        remove_worktree_file(work_dir, path)?;
+        index.remove(path);
+    }
+    for (path, entry) in &updates {
+        let Some((mode, sha)) = entry else { continue };
+        write_worktree_file(&work_dir.join(path), mode, &read_object(repo_path, sha)?)?;
+        index.insert(path.clone(), (mode.clone(), sha.clone()));
+    }
 
-        if path.is_dir() {
-            fs::remove_dir_all(&path)?;
-        } else {
-            fs::remove_file(&path)?;
+    let mut entries: Vec<IndexEntry> = index
+        .iter()
+        .map(|(path, (mode, sha1))| IndexEntry { mode: mode.clone(), sha1: sha1.clone(), path: path.clone() })
+        .collect();
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    write_index(repo_path, &entries)?;
+
+    // 3. Report what is still different from the new commit
+    let mut carried = Vec::new();
+    for entry in &entries {
+        let status = match new.get(&entry.path) {
+            None => Some('A'),
+            Some((_, sha)) if sha != &entry.sha1 => Some('M'),
+            Some(_) => match worktree_hash(&entry.path)? {
+                None => Some('D'),
+                Some(hash) if hash != entry.sha1 => Some('M'),
+                Some(_) => None,
+            },
+        };
+        if let Some(status) = status {
+            carried.push((status, entry.path.clone()));
         }
     }
-    Ok(())
+    for path in new.keys().filter(|path| !index.contains_key(*path)) {
+        carried.push(('D', path.clone()));
+    }
+    carried.sort_by(|a, b| a.1.cmp(&b.1));
+    Ok(carried)
 }
 
-fn restore_tree(repo_path: &Path, tree_hash: &str, target_dir: &Path) -> io::Result<()> {
-    let tree_data = read_object(repo_path, tree_hash)?;
-    let mut pos = 0;
-    while pos < tree_data.len() {
-        let mut end = pos;
-        while end < tree_data.len() && tree_data[end] != 0 {
-            end += 1;
-        }
-        if end >= tree_data.len() { break; }
-
-        let entry_str = String::from_utf8_lossy(&tree_data[pos..end]);
-        let (mode, filename) = entry_str.split_once(' ').ok_or_else(|| io::Error::other("Malformed tree entry"))?;
-        
-        let sha_start = end + 1;
-        let sha_end = sha_start + 20;
-        if sha_end > tree_data.len() { break; }
-
-        let sha_bytes = &tree_data[sha_start..sha_end];
-        let sha1 = hex::encode(sha_bytes);
-        let path = target_dir.join(filename);
-
-        if mode == "40000" {
-            fs::create_dir_all(&path)?;
-            restore_tree(repo_path, &sha1, &path)?;
-        } else {
-            let blob_data = read_object(repo_path, &sha1)?;
-            let mut file = File::create(&path)?;
-            file.write_all(&blob_data)?;
+/// Before moving away from a detached HEAD, warns about the commits that
+/// would become unreachable: those not contained in any branch or in the
+/// commit being checked out.
+pub(crate) fn warn_leaving_detached(repo_path: &Path, old_head: &str, new_commit: &str) -> io::Result<()> {
+    if old_head == new_commit {
+        return Ok(());
+    }
+    let mut kept = ancestors(repo_path, new_commit)?;
+    let mut tips: Vec<String> = list_refs(repo_path, "heads")?.into_iter().map(|(_, tip)| tip).collect();
+    // Tags keep commits too, once peeled; tags of trees or blobs don't count
+    for (_, tag) in list_refs(repo_path, "tags")? {
+        tips.extend(peel(repo_path, &tag, "commit").ok());
+    }
+    for tip in tips {
+        if !kept.contains(&tip) {
+            kept.extend(ancestors(repo_path, &tip)?);
         }
-        pos = sha_end;
     }
+
+    // Newest first, like log
+    let orphaned = walk_commits(repo_path, &[old_head.to_string()], &kept, false)?;
+    if orphaned.is_empty() {
+        let summary = read_commit(repo_path, old_head)?.summary().to_string();
+        eprintln!("Previous HEAD position was {} {}", &old_head[..7], summary);
+        return Ok(());
+    }
+
+    const SHOWN: usize = 4;
+    let count = orphaned.len();
+    eprintln!(
+        "Warning: you are leaving {} commit{} behind, not connected to\nany of your branches:\n",
+        count,
+        if count == 1 { "" } else { "s" }
+    );
+    for (hash, commit) in orphaned.iter().take(SHOWN) {
+        eprintln!("  {} {}", &hash[..7], commit.summary());
+    }
+    if count > SHOWN {
+        eprintln!(" ... and {} more.", count - SHOWN);
+    }
+    eprintln!(
+        "\nIf you want to keep {} by creating a new branch, this may be a good time\nto do so with:\n\n rit branch <new-branch-name> {}\n",
+        if count == 1 { "it" } else { "them" },
+        &old_head[..7]
+    );
     Ok(())
-}
\ No newline at end of file
+}
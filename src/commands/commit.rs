@@ -1,43 +1,218 @@
-use super::utils::{find_repo_root, update_head, write_object};
+use super::config::{get_value, read_config_file};
+use super::date::{format_offset, local_offset, parse_signature_date};
+use super::interpret_trailers::{add_trailers, parse_trailers, IfExists, Trailer};
+use super::diff::{changes_between, ChangeKind, FileMap};
+use super::merge::{clear_merge_state, mark_resolved, merge_head, read_conflicts};
+use super::revision::resolve_commit;
+use super::utils::{
+    blob_object, find_repo_root, get_current_branch, read_head_commit, read_object, read_tree_entries,
+    read_worktree_file, trust_file_mode, update_head, write_object, MODE_SYMLINK,
+};
 use sha1::{Digest, Sha1};
-use std::collections::{BTreeMap, HashMap};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
-use std::io::{self};
+use std::io::{self, Read};
 use std::path::{Path, PathBuf};
+use std::process::Command;
 use std::time::{SystemTime, UNIX_EPOCH};
 
-// Helper function to read the .rit/config file
-fn read_config(repo_path: &Path) -> io::Result<HashMap<String, String>> {
-    let config_path = repo_path.join("config");
-    let mut config = HashMap::new();
-    if !config_path.exists() {
-        return Ok(config);
+pub(crate) struct IndexEntry {
+    pub mode: String,
+    pub sha1: String,
+    pub path: PathBuf,
+}
+
+/// The identity and time recorded on an `author` or `committer` line:
+/// `Name <email> 1700000000 +0530`.
+#[derive(Debug, Clone)]
+pub(crate) struct Signature {
+    pub name: String,
+    pub email: String,
+    pub timestamp: i64,
+    pub timezone: String,
+}
+
+impl Signature {
+    pub fn parse(line: &str) -> Signature {
+        let (name, rest) = line.split_once('<').unwrap_or((line, ""));
+        let (email, rest) = rest.split_once('>').unwrap_or((rest, ""));
+        let mut when = rest.split_whitespace();
+        Signature {
+            name: name.trim().to_string(),
+            email: email.trim().to_string(),
+            timestamp: when.next().and_then(|t| t.parse().ok()).unwrap_or(0),
+            timezone: when.next().unwrap_or("+0000").to_string(),
+        }
     }
 
-    let content = fs::read_to_string(config_path)?;
-    for line in content.lines() {
-        // Simple key = value parser, ignores sections like [user]
-        if let Some((key, value)) = line.trim().split_once('=') {
-            config.insert(key.trim().to_string(), value.trim().to_string());
+    /// A signature for the current time, in the local timezone.
+    pub fn now(name: &str, email: &str) -> Signature {
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+        Signature {
+            name: name.to_string(),
+            email: email.to_string(),
+            timestamp,
+            timezone: format_offset(local_offset(timestamp)),
         }
     }
-    Ok(config)
+
+    /// The timezone as an offset from UTC in seconds.
+    pub fn offset_seconds(&self) -> i64 {
+        let sign = if self.timezone.starts_with('-') { -1 } else { 1 };
+        let digits = self.timezone.trim_start_matches(['+', '-']);
+        let hours: i64 = digits.get(..2).and_then(|h| h.parse().ok()).unwrap_or(0);
+        let minutes: i64 = digits.get(2..4).and_then(|m| m.parse().ok()).unwrap_or(0);
+        sign * (hours * 3600 + minutes * 60)
+    }
 }
 
-pub(crate) struct IndexEntry {
-    pub mode: String,
-    pub sha1: String,
-    pub path: PathBuf,
+impl fmt::Display for Signature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} <{}> {} {}", self.name, self.email, self.timestamp, self.timezone)
+    }
 }
 
-pub fn run(message: &str) -> io::Result<()> {
-    // 1. Locate repository root and read config
+/// Who is making a commit or tag, `role` being `author` or `committer`.
+/// `RIT_AUTHOR_NAME`, `RIT_AUTHOR_EMAIL` and `RIT_AUTHOR_DATE` (or the
+/// `RIT_COMMITTER_*` ones) take precedence over `user.name`, `user.email`
+/// and the current time.
+pub(crate) fn identity(repo_path: &Path, role: &str) -> io::Result<Signature> {
+    let config = read_config_file(repo_path)?;
+    let env = |field: &str| {
+        let value = std::env::var(format!("RIT_{}_{}", role.to_uppercase(), field)).ok();
+        value.filter(|value| !value.trim().is_empty())
+    };
+    let config_value = |key: &str| config.get(key).filter(|value| !value.is_empty()).cloned();
+
+    let (Some(name), Some(email)) = (env("NAME").or(config_value("user.name")), env("EMAIL").or(config_value("user.email"))) else {
+        let mut role = role.to_string();
+        role[..1].make_ascii_uppercase();
+        return Err(io::Error::other(format!(
+            "{} identity unknown\n\n*** Please tell me who you are.\n\nRun\n\n  rit config user.email \"you@example.com\"\n  rit config user.name \"Your Name\"\n\nto set your account's default identity.",
+            role
+        )));
+    };
+    dated_signature(role, &name, &email)
+}
+
+/// A signature for `name` and `email` made now, or at `RIT_<ROLE>_DATE`
+/// when that is set.
+fn dated_signature(role: &str, name: &str, email: &str) -> io::Result<Signature> {
+    let mut signature = Signature::now(name, email);
+    let date = std::env::var(format!("RIT_{}_DATE", role.to_uppercase())).ok();
+    if let Some(date) = date.filter(|date| !date.trim().is_empty()) {
+        (signature.timestamp, signature.timezone) = parse_signature_date(&date).map_err(io::Error::other)?;
+    }
+    Ok(signature)
+}
+
+/// A parsed commit object.
+#[derive(Debug, Clone)]
+pub(crate) struct Commit {
+    pub tree: String,
+    pub parents: Vec<String>,
+    pub author: Signature,
+    pub committer: Signature,
+    pub message: String,
+}
+
+impl Commit {
+    pub fn parse(data: &[u8]) -> Commit {
+        let content = String::from_utf8_lossy(data);
+        let (headers, message) = content.split_once("\n\n").unwrap_or((&content, ""));
+
+        let mut tree = String::new();
+        let mut parents = Vec::new();
+        let mut author = None;
+        let mut committer = None;
+        for line in headers.lines() {
+            if let Some(hash) = line.strip_prefix("tree ") {
+                tree = hash.to_string();
+            } else if let Some(hash) = line.strip_prefix("parent ") {
+                parents.push(hash.to_string());
+            } else if let Some(sig) = line.strip_prefix("author ") {
+                author = Some(Signature::parse(sig));
+            } else if let Some(sig) = line.strip_prefix("committer ") {
+                committer = Some(Signature::parse(sig));
+            }
+        }
+        let author = author.unwrap_or_else(|| Signature::parse(""));
+        Commit {
+            tree,
+            parents,
+            committer: committer.unwrap_or_else(|| author.clone()),
+            author,
+            message: message.to_string(),
+        }
+    }
+
+    /// The first line of the commit message.
+    pub fn summary(&self) -> &str {
+        self.message.lines().next().unwrap_or("").trim()
+    }
+
+    /// The `Key: value` trailers at the end of the message.
+    pub fn trailers(&self) -> Vec<Trailer> {
+        parse_trailers(&self.message)
+    }
+
+    /// The message after its summary line and the blank line following it.
+    pub fn body(&self) -> String {
+        let body: Vec<&str> = self.message.lines().skip(1).skip_while(|line| line.trim().is_empty()).collect();
+        body.join("\n").trim_end().to_string()
+    }
+}
+
+/// Reads and parses a commit object.
+pub(crate) fn read_commit(repo_path: &Path, hash: &str) -> io::Result<Commit> {
+    Ok(Commit::parse(&read_object(repo_path, hash)?))
+}
+
+/// Options for `rit commit`.
+#[derive(Debug, Default)]
+pub struct CommitOptions {
+    /// `-m` paragraphs; without them (or `-F`) the editor is opened
+    pub message: Vec<String>,
+    /// `-F`: read the message from this file, or stdin for `-`
+    pub file: Option<String>,
+    /// Use the message as it stands (e.g. the amended one) without editing
+    pub no_edit: bool,
+    /// Replace HEAD instead of adding a commit on top of it
+    pub amend: bool,
+    /// Record a commit even if its tree is the same as its parent's
+    pub allow_empty: bool,
+    /// Mark the commit as a fixup of this revision (`fixup! <summary>`)
+    pub fixup: Option<String>,
+    /// Mark the commit as a squash onto this revision (`squash! <summary>`)
+    pub squash: Option<String>,
+    /// Stage changes to all tracked files first
+    pub all: bool,
+    /// `--author="Name <email>"`: who wrote the change, if not the committer
+    pub author: Option<String>,
+    /// `--date`: when the change was written
+    pub date: Option<String>,
+    /// `-s`: add a `Signed-off-by` trailer for the committer
+    pub signoff: bool,
+    /// `--trailer`: more trailers to add, as `key=value`
+    pub trailers: Vec<String>,
+}
+
+pub fn run(options: &CommitOptions) -> io::Result<()> {
+    // 1. Locate repository root, and check who is committing before
+    //    anything is staged or written
     let repo_path = find_repo_root()?;
-    let config = read_config(&repo_path)?;
+    let committer = identity(&repo_path, "committer")?;
+    if options.author.is_none() && !options.amend {
+        identity(&repo_path, "author")?;
+    }
 
-    // 2. Read the index
+    // 2. Read the index, after staging tracked files with -a
+    if options.all {
+        stage_tracked_changes(&repo_path)?;
+    }
     let index_entries = read_index(&repo_path)?;
-    if index_entries.is_empty() {
+    if index_entries.is_empty() && !options.allow_empty && !options.amend {
         println!("Nothing to commit");
         return Ok(());
     }
@@ -45,28 +220,313 @@ pub fn run(message: &str) -> io::Result<()> {
     // 3. Build tree object from index
     let tree_hash = write_tree(&repo_path, &index_entries)?;
 
-    // 4. Get parent commit (if HEAD exists)
-    let parent_hash = read_head(&repo_path)?;
+    // 4. Get the parents: HEAD, or HEAD's own parents when amending it. A
+    //    merge in progress adds the merged-in commit as a second parent once
+    //    its conflicts are resolved.
+    let head = read_head_commit(&repo_path)?;
+    let merge_head = merge_head(&repo_path)?;
+    let amended = match (options.amend, &head) {
+        (false, _) => None,
+        (true, None) => return Err(io::Error::other("You have nothing to amend.")),
+        (true, Some(_)) if merge_head.is_some() => {
+            return Err(io::Error::other("You are in the middle of a merge -- cannot amend."));
+        }
+        (true, Some(head)) => Some(read_commit(&repo_path, head)?),
+    };
+    let mut parents = match &amended {
+        Some(commit) => commit.parents.clone(),
+        None => head.iter().cloned().collect(),
+    };
+    if let Some(merge_head) = &merge_head {
+        let unresolved = read_conflicts(&repo_path)?;
+        if !unresolved.is_empty() {
+            let paths: Vec<String> = unresolved.iter().map(|c| c.path.display().to_string()).collect();
+            return Err(io::Error::other(format!(
+                "Committing is not possible because you have unmerged files:\n  {}\n\nFix them up in the work tree, then use 'rit add <file>' or 'rit mergetool'.",
+                paths.join("\n  ")
+            )));
+        }
+        if head.is_some() {
+            parents.push(merge_head.clone());
+        }
+    }
 
-    // 5. Build commit object content, now passing the config
-    let commit_content = build_commit_content(&tree_hash, parent_hash.as_deref(), message, &config);
+    // 5. Refuse a commit that changes nothing, unless asked for one
+    if merge_head.is_none() && !options.allow_empty {
+        let parent_tree = match parents.first() {
+            Some(parent) => Some(read_commit(&repo_path, parent)?.tree),
+            None => None,
+        };
+        if parent_tree.as_ref() == Some(&tree_hash) {
+            return Err(io::Error::other(match options.amend {
+                true => "You asked to amend the most recent commit, but doing so would make\nit empty. Use --allow-empty to record it anyway.",
+                false => "nothing to commit, working tree clean (use --allow-empty to record an empty commit)",
+            }));
+        }
+    }
 
-    // 6. Hash commit object to get its ID
+    // 6. Work out the message: -m or -F, a fixup/squash subject that
+    //    `rebase --autosquash` can recognise, or one written in the editor
+    let mut message = commit_message(&repo_path, options, amended.as_ref(), parents.first().map(String::as_str), &index_entries)?;
+    let mut trailers: Vec<Trailer> = options.trailers.iter().map(|arg| Trailer::parse_arg(arg)).collect::<io::Result<_>>()?;
+    if options.signoff {
+        trailers.push(Trailer { key: "Signed-off-by".to_string(), value: format!("{} <{}>", committer.name, committer.email) });
+    }
+    if !trailers.is_empty() {
+        message = add_trailers(&message, &trailers, IfExists::default());
+    }
+
+    // 7. Build the commit object. Amending keeps the original author, and
+    //    --author and --date override who and when.
+    let mut author = match (&options.author, &amended) {
+        (None, Some(commit)) => commit.author.clone(),
+        (None, None) => identity(&repo_path, "author")?,
+        (Some(given), amended) => {
+            let (name, email) = parse_author(given)?;
+            match amended {
+                Some(commit) => Signature { name, email, ..commit.author.clone() },
+                None => dated_signature("author", &name, &email)?,
+            }
+        }
+    };
+    if let Some(date) = &options.date {
+        (author.timestamp, author.timezone) = parse_signature_date(date).map_err(io::Error::other)?;
+    }
+    let parent_lines: String = parents.iter().map(|parent| format!("parent {}\n", parent)).collect();
+    let commit_content = commit_content(&tree_hash, &parent_lines, &author, &committer, &message);
+
+    // 8. Hash commit object to get its ID, and write it to the object database
     let mut hasher = Sha1::new();
     hasher.update(&commit_content);
     let commit_hash = hex::encode(hasher.finalize());
-
-    // 7. Write commit object to the object database
     write_object(&repo_path, &commit_hash, commit_content.as_bytes())?;
 
-    // 8. Update HEAD (the current branch) to point at the new commit
-    update_head(&repo_path, &commit_hash)?;
+    // 9. Update HEAD (the current branch) to point at the new commit
+    let kind = match (&amended, &head, &merge_head) {
+        (Some(_), _, _) => "commit (amend)",
+        (_, None, _) => "commit (initial)",
+        (_, _, Some(_)) => "commit (merge)",
+        _ => "commit",
+    };
+    let summary = message.lines().next().unwrap_or("");
+    update_head(&repo_path, &commit_hash, &format!("{}: {}", kind, summary))?;
+    if merge_head.is_some() {
+        clear_merge_state(&repo_path)?;
+    }
 
-    let current_branch = super::utils::get_current_branch()?.unwrap_or_else(|| "main".to_string());
-    println!("[{} {}] {}", current_branch, &commit_hash[..7], message);
+    let current_branch = get_current_branch()?.unwrap_or_else(|| "detached HEAD".to_string());
+    println!("[{} {}] {}", current_branch, &commit_hash[..7], summary);
     Ok(())
 }
 
+/// The message for a new commit: from -m or -F, a fixup subject, or else
+/// whatever is left after editing `.rit/COMMIT_EDITMSG`.
+fn commit_message(
+    repo_path: &Path,
+    options: &CommitOptions,
+    amended: Option<&Commit>,
+    parent: Option<&str>,
+    index_entries: &[IndexEntry],
+) -> io::Result<String> {
+    // -m paragraphs are separated by a blank line, like separate -m in git
+    let given = match (&options.message[..], &options.file) {
+        ([], None) => None,
+        ([], Some(file)) => Some(read_message_file(file)?),
+        (paragraphs, _) => Some(paragraphs.join("\n\n")),
+    };
+
+    // The initial message, before any editing
+    let prefixed = match (&options.fixup, &options.squash) {
+        (Some(rev), _) => Some(("fixup!", rev)),
+        (_, Some(rev)) => Some(("squash!", rev)),
+        _ => None,
+    };
+    let template = commit_template(repo_path)?;
+    let initial = if let Some((prefix, rev)) = prefixed {
+        let target = read_commit(repo_path, &resolve_commit(repo_path, rev)?)?;
+        let subject = format!("{} {}", prefix, target.summary());
+        match &given {
+            Some(body) => format!("{}\n\n{}", subject, body),
+            None => subject,
+        }
+    } else if let Some(message) = given {
+        message
+    } else if let Some(commit) = amended {
+        commit.message.clone()
+    } else if let Some(message) = read_optional(&repo_path.join("MERGE_MSG"))? {
+        message
+    } else {
+        template.clone().unwrap_or_default()
+    };
+
+    // A fixup needs no editing, and neither does a message from -m or -F
+    let edit = !options.no_edit
+        && options.fixup.is_none()
+        && options.message.is_empty()
+        && options.file.is_none();
+    if !edit {
+        let message = cleanup_message(&initial, false);
+        if message.is_empty() {
+            return Err(io::Error::other("Aborting commit due to empty commit message."));
+        }
+        return Ok(message);
+    }
+
+    let path = repo_path.join("COMMIT_EDITMSG");
+    let status = commit_status_comment(repo_path, parent, index_entries)?;
+    fs::write(&path, format!("{}\n{}", initial.trim_end(), status))?;
+    launch_editor(repo_path, &path)?;
+
+    let message = cleanup_message(&fs::read_to_string(&path)?, true);
+    if message.is_empty() {
+        return Err(io::Error::other("Aborting commit due to empty commit message."));
+    }
+    if template.is_some_and(|template| cleanup_message(&template, true) == message) {
+        return Err(io::Error::other("Aborting commit; you did not edit the message."));
+    }
+    Ok(message)
+}
+
+/// Reads a message for -F, where `-` means standard input.
+fn read_message_file(file: &str) -> io::Result<String> {
+    if file == "-" {
+        let mut message = String::new();
+        io::stdin().read_to_string(&mut message)?;
+        return Ok(message);
+    }
+    fs::read_to_string(file).map_err(|e| io::Error::other(format!("could not read log file '{}': {}", file, e)))
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match path.is_file() {
+        true => Ok(Some(fs::read_to_string(path)?)),
+        false => Ok(None),
+    }
+}
+
+/// The contents of the file named by `commit.template`, relative to the
+/// working directory, with `~/` meaning the home directory.
+fn commit_template(repo_path: &Path) -> io::Result<Option<String>> {
+    let Some(name) = get_value(repo_path, "commit.template")? else {
+        return Ok(None);
+    };
+    let path = match (name.strip_prefix("~/"), std::env::var_os("HOME")) {
+        (Some(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => PathBuf::from(&name),
+    };
+    fs::read_to_string(&path)
+        .map(Some)
+        .map_err(|e| io::Error::other(format!("could not read commit template '{}': {}", name, e)))
+}
+
+/// The commented-out help and summary of staged changes shown below the
+/// message in the editor.
+fn commit_status_comment(repo_path: &Path, parent: Option<&str>, index_entries: &[IndexEntry]) -> io::Result<String> {
+    let mut comment = String::from(
+        "\n# Please enter the commit message for your changes. Lines starting\n# with '#' will be ignored, and an empty message aborts the commit.\n#\n",
+    );
+    match get_current_branch()? {
+        Some(branch) => comment.push_str(&format!("# On branch {}\n", branch)),
+        None => comment.push_str("# HEAD detached\n"),
+    }
+
+    let parent_tree = match parent {
+        Some(parent) => read_tree_entries(repo_path, &read_commit(repo_path, parent)?.tree)?,
+        None => FileMap::new(),
+    };
+    let index: FileMap = index_entries
+        .iter()
+        .map(|entry| (entry.path.clone(), (entry.mode.clone(), entry.sha1.clone())))
+        .collect();
+    let changes = changes_between(repo_path, &parent_tree, &index)?;
+    if !changes.is_empty() {
+        comment.push_str("#\n# Changes to be committed:\n");
+        for change in &changes {
+            let label = match change.kind {
+                ChangeKind::Added => "new file:",
+                ChangeKind::Deleted => "deleted:",
+                _ => "modified:",
+            };
+            comment.push_str(&format!("#\t{:<12}{}\n", label, change.path.display()));
+        }
+    }
+    comment.push_str("#\n");
+    Ok(comment)
+}
+
+/// Opens the user's editor on `path` and waits for it to exit. The editor
+/// is `$RIT_EDITOR`, `core.editor`, `$VISUAL` or `$EDITOR`, whichever is set
+/// first, and `vi` otherwise.
+fn launch_editor(repo_path: &Path, path: &Path) -> io::Result<()> {
+    let from_env = |name: &str| std::env::var(name).ok().filter(|value| !value.is_empty());
+    let editor = from_env("RIT_EDITOR")
+        .or(get_value(repo_path, "core.editor")?)
+        .or_else(|| from_env("VISUAL"))
+        .or_else(|| from_env("EDITOR"))
+        .unwrap_or_else(|| "vi".to_string());
+
+    // Run through the shell so the editor may carry its own arguments
+    let status = Command::new("sh")
+        .arg("-c")
+        .arg(format!("{} \"$@\"", editor))
+        .arg(&editor)
+        .arg(path)
+        .status()
+        .map_err(|e| io::Error::other(format!("unable to start editor '{}': {}", editor, e)))?;
+    if !status.success() {
+        return Err(io::Error::other(format!(
+            "There was a problem with the editor '{}'.\nPlease supply the message using either -m or -F option.",
+            editor
+        )));
+    }
+    Ok(())
+}
+
+/// Tidies a commit message: trailing whitespace and surplus blank lines
+/// go, and with `strip_comments` so do lines starting with `#`.
+fn cleanup_message(message: &str, strip_comments: bool) -> String {
+    let mut lines: Vec<&str> = Vec::new();
+    for line in message.lines() {
+        if strip_comments && line.starts_with('#') {
+            continue;
+        }
+        let line = line.trim_end();
+        if line.is_empty() && lines.last().is_none_or(|last| last.is_empty()) {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    lines.join("\n")
+}
+
+/// `rit commit -a`: stages the current contents of every tracked file, and
+/// removes tracked files that were deleted. New files are left alone.
+fn stage_tracked_changes(repo_path: &Path) -> io::Result<()> {
+    let work_dir = repo_path.parent().unwrap();
+    let keep_mode = !trust_file_mode(repo_path)?;
+    let mut entries = Vec::new();
+    for entry in read_index(repo_path)? {
+        let Some((mode, contents)) = read_worktree_file(&work_dir.join(&entry.path))? else {
+            mark_resolved(repo_path, &entry.path)?;
+            continue;
+        };
+        let (sha1, blob) = blob_object(&contents);
+        if sha1 != entry.sha1 {
+            write_object(repo_path, &sha1, &blob)?;
+            mark_resolved(repo_path, &entry.path)?;
+        }
+        let mode = match keep_mode && mode != MODE_SYMLINK && entry.mode != MODE_SYMLINK {
+            true => entry.mode,
+            false => mode,
+        };
+        entries.push(IndexEntry { mode, sha1, path: entry.path });
+    }
+    write_index(repo_path, &entries)
+}
+
 /// Read index and parse into Vec<IndexEntry>
 pub fn read_index(repo_path: &Path) -> io::Result<Vec<IndexEntry>> {
     let index_path = repo_path.join("index");
@@ -92,6 +552,15 @@ pub fn read_index(repo_path: &Path) -> io::Result<Vec<IndexEntry>> {
     Ok(entries)
 }
 
+/// Write a list of entries back to the index, one `<mode> <sha1> <path>` per line
+pub fn write_index(repo_path: &Path, entries: &[IndexEntry]) -> io::Result<()> {
+    let content: String = entries
+        .iter()
+        .map(|entry| format!("{} {} {}\n", entry.mode, entry.sha1, entry.path.display()))
+        .collect();
+    fs::write(repo_path.join("index"), content)
+}
+
 /// Build tree object recursively from a flat list of index entries
 pub fn write_tree(repo_path: &Path, index_entries: &[IndexEntry]) -> io::Result<String> {
     let entry_refs: Vec<&IndexEntry> = index_entries.iter().collect();
@@ -163,83 +632,39 @@ fn build_tree_recursive(
     Ok(tree_hash)
 }
 
-/// Read HEAD and return parent commit hash (if any)
-fn read_head(repo_path: &Path) -> io::Result<Option<String>> {
-    let head_path = repo_path.join("HEAD");
-    if !head_path.exists() {
-        return Ok(None);
-    }
-
-    let content = fs::read_to_string(head_path)?.trim().to_string();
-
-    if let Some(ref_path) = content.strip_prefix("ref: ") {
-        let branch_path = repo_path.join(ref_path);
-        if branch_path.exists() {
-            let hash = fs::read_to_string(branch_path)?.trim().to_string();
-            if hash.is_empty() {
-                Ok(None)
-            } else {
-                Ok(Some(hash))
-            }
-        } else {
-            Ok(None) // Branch file doesn't exist yet
-        }
-    } else if content.len() == 40 {
-        Ok(Some(content)) // Detached HEAD
-    } else {
-        Ok(None)
-    }
-}
-
-/// Build commit content string using author info from config
+/// Build commit content string, with the author and committer from
+/// [`identity`]
 pub fn build_commit_content(
+    repo_path: &Path,
     tree_hash: &str,
     parent_hash: Option<&str>,
     message: &str,
-    config: &HashMap<String, String>,
-) -> String {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .unwrap()
-        .as_secs();
-
-    let author_name = config
-        .get("name")
-        .cloned()
-        .unwrap_or_else(|| "User".to_string());
-    let author_email = config
-        .get("email")
-        .cloned()
-        .unwrap_or_else(|| "user@example.com".to_string());
-
-    let author_line = format!(
-        "author {} <{}> {} +0530",
-        author_name, author_email, timestamp
-    );
-    let committer_line = format!(
-        "committer {} <{}> {} +0530",
-        author_name, author_email, timestamp
-    );
-
-    let mut content = String::new();
-    content.push_str(&format!("tree {}\n", tree_hash));
+) -> io::Result<String> {
+    let author = identity(repo_path, "author")?;
+    let committer = identity(repo_path, "committer")?;
+
+    // This handles both single parents (just a hash) and merge commits
+    // (which pass a pre-formatted string with multiple "parent .." lines)
+    let parent_lines = match parent_hash {
+        Some(parent) if parent.contains("parent ") => parent.to_string(),
+        Some(parent) => format!("parent {}\n", parent),
+        None => String::new(),
+    };
+    Ok(commit_content(tree_hash, &parent_lines, &author, &committer, message))
+}
 
-    if let Some(parent) = parent_hash {
-        // This logic handles both single parents (just a hash) and
-        // merge commits (which pass a pre-formatted string with multiple "parent .." lines)
-        if parent.contains("parent ") {
-            content.push_str(parent);
-        } else {
-            content.push_str(&format!("parent {}\n", parent));
-        }
+/// Splits `--author="Name <email>"` into its name and email.
+fn parse_author(author: &str) -> io::Result<(String, String)> {
+    let invalid = || io::Error::other(format!("--author '{}' is not 'Name <email>'", author));
+    let (name, rest) = author.split_once('<').ok_or_else(invalid)?;
+    let email = rest.strip_suffix('>').map(str::trim).unwrap_or_default();
+    if name.trim().is_empty() || email.is_empty() {
+        return Err(invalid());
     }
+    Ok((name.trim().to_string(), email.to_string()))
+}
 
-    content.push_str(&author_line);
-    content.push('\n');
-    content.push_str(&committer_line);
-    content.push('\n');
-    content.push('\n');
-    content.push_str(message);
-
-    content
+/// The text of a commit object, with `parent_lines` already formatted.
+fn commit_content(tree_hash: &str, parent_lines: &str, author: &Signature, committer: &Signature, message: &str) -> String {
+    format!("tree {}\n{}author {}\ncommitter {}\n\n{}", tree_hash, parent_lines, author, committer, message)
 }
@@ -0,0 +1,41 @@
+use super::commit::build_commit_content;
+use super::revision::{peel, resolve_commit, resolve_revision};
+use super::utils::{find_repo_root, write_object};
+use sha1::{Digest, Sha1};
+use std::io::{self, Read};
+
+/// Creates a commit object for an existing tree and prints its hash,
+/// without touching HEAD or any branch.
+pub fn run(tree: &str, parents: &[String], message: Option<&str>) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+
+    let tree_hash = peel(&repo_path, &resolve_revision(&repo_path, tree)?, "tree")
+        .map_err(|_| io::Error::other(format!("{} is not a valid tree object", tree)))?;
+
+    let mut parent_lines = String::new();
+    for parent in parents {
+        let hash = resolve_commit(&repo_path, parent)?;
+        parent_lines.push_str(&format!("parent {}\n", hash));
+    }
+
+    // Without -m the message is read from stdin
+    let message = match message {
+        Some(message) => message.to_string(),
+        None => {
+            let mut message = String::new();
+            io::stdin().read_to_string(&mut message)?;
+            message
+        }
+    };
+
+    let parent = (!parent_lines.is_empty()).then_some(parent_lines.as_str());
+    let commit_content = build_commit_content(&repo_path, &tree_hash, parent, &message)?;
+
+    let mut hasher = Sha1::new();
+    hasher.update(&commit_content);
+    let commit_hash = hex::encode(hasher.finalize());
+
+    write_object(&repo_path, &commit_hash, commit_content.as_bytes())?;
+    println!("{}", commit_hash);
+    Ok(())
+}
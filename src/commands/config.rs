@@ -1,9 +1,10 @@
 // In commands/config.rs
 
 use super::utils::find_repo_root;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::io::{self, BufRead, Write};
+use std::path::Path;
 
 /// The new run function signature matches how it's called in main.rs
 pub fn run(key: &str, value: Option<&str>) -> io::Result<()> {
@@ -17,10 +18,12 @@ pub fn run(key: &str, value: Option<&str>) -> io::Result<()> {
     Ok(())
 }
 
-/// Reads the config file into a HashMap, understanding [sections]
-fn read_config_file() -> io::Result<HashMap<String, String>> {
-    let repo_root = find_repo_root()?;
-    let config_path = repo_root.join("config");
+/// Reads the config file into a HashMap, understanding [sections] and
+/// [section "subsection"] headers.
+///
+/// Keys are flattened to `section.key` or `section.subsection.key`.
+pub(crate) fn read_config_file(repo_path: &Path) -> io::Result<HashMap<String, String>> {
+    let config_path = repo_path.join("config");
     let mut config = HashMap::new();
     let mut current_section = String::new();
 
@@ -31,7 +34,7 @@ fn read_config_file() -> io::Result<HashMap<String, String>> {
         for line in reader.lines() {
             let line = line?.trim().to_string();
             if line.starts_with('[') && line.ends_with(']') {
-                current_section = line[1..line.len() - 1].to_string();
+                current_section = parse_section_header(&line[1..line.len() - 1]);
             } else if let Some((key, value)) = line.split_once('=') {
                 let full_key = format!("{}.{}", current_section, key.trim());
                 config.insert(full_key, value.trim().to_string());
@@ -41,31 +44,62 @@ fn read_config_file() -> io::Result<HashMap<String, String>> {
     Ok(config)
 }
 
+/// Looks up a single configuration value, e.g. `diff.wordRegex`.
+pub(crate) fn get_value(repo_path: &Path, key: &str) -> io::Result<Option<String>> {
+    Ok(read_config_file(repo_path)?.remove(key))
+}
+
+/// Turns `diff "rs"` into `diff.rs`; plain section names are kept as they are.
+fn parse_section_header(header: &str) -> String {
+    match header.split_once(' ') {
+        Some((section, subsection)) => {
+            format!("{}.{}", section.trim(), subsection.trim().trim_matches('"'))
+        }
+        None => header.trim().to_string(),
+    }
+}
+
+/// Splits `section.sub.key` into the header it lives under and the bare key.
+fn split_key(full_key: &str) -> (String, &str) {
+    let (section, key) = full_key.rsplit_once('.').unwrap_or(("core", full_key));
+    let header = match section.split_once('.') {
+        Some((section, subsection)) => format!("{} \"{}\"", section, subsection),
+        None => section.to_string(),
+    };
+    (header, key)
+}
+
 /// Sets a single configuration key-value pair
 fn set_config(key: &str, value: &str) -> io::Result<()> {
     let repo_root = find_repo_root()?;
     let config_path = repo_root.join("config");
-    let mut config = read_config_file()?;
+    let mut config = read_config_file(&repo_root)?;
     config.insert(key.to_string(), value.to_string());
 
-    let mut file = fs::File::create(config_path)?;
-    writeln!(file, "[user]")?;
-    if let Some(name) = config.get("user.name") {
-        writeln!(file, "  name = {}", name)?;
+    // Group keys by section so every section is written exactly once
+    let mut sections: BTreeMap<String, BTreeMap<&str, &str>> = BTreeMap::new();
+    for (full_key, value) in &config {
+        let (header, key) = split_key(full_key);
+        sections.entry(header).or_default().insert(key, value);
     }
-    if let Some(email) = config.get("user.email") {
-        writeln!(file, "  email = {}", email)?;
+
+    let mut file = fs::File::create(config_path)?;
+    for (header, entries) in sections {
+        writeln!(file, "[{}]", header)?;
+        for (key, value) in entries {
+            writeln!(file, "  {} = {}", key, value)?;
+        }
     }
-    
+
     // We don't need a "Set..." message here anymore, clap handles feedback.
     Ok(())
 }
 
 /// Gets and prints a configuration value by its key
 fn get_config(key: &str) -> io::Result<()> {
-    let config = read_config_file()?;
-    if let Some(value) = config.get(key) {
+    let repo_root = find_repo_root()?;
+    if let Some(value) = get_value(&repo_root, key)? {
         println!("{}", value);
     }
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,216 @@
+use jiff::tz::TimeZone;
+use jiff::Timestamp;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Parses the dates accepted by `--since` and `--until`: `2024-01-31`,
+/// `2024-01-31 14:00[:00]`, a Unix timestamp, `now`, `yesterday` or a
+/// relative `<n> <unit>s ago` such as `2 weeks ago` (or `2.weeks.ago`).
+pub(crate) fn parse_date(value: &str) -> Result<i64, String> {
+    let mut value = value.trim().to_lowercase();
+    if value.ends_with(".ago") {
+        value = value.replace('.', " ");
+    }
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let invalid = || format!("invalid date '{}'", value);
+
+    match value.as_str() {
+        "now" => return Ok(now),
+        "today" => return Ok(now - now.rem_euclid(86400)),
+        "yesterday" => return Ok(now - 86400),
+        _ => {}
+    }
+    if let Ok(timestamp) = value.parse::<i64>() {
+        return Ok(timestamp);
+    }
+
+    if let Some(amount) = value.strip_suffix(" ago") {
+        let (count, unit) = amount.split_once(' ').ok_or_else(invalid)?;
+        let count: i64 = count.parse().map_err(|_| invalid())?;
+        let seconds = match unit.trim_end_matches('s') {
+            "second" => 1,
+            "minute" => 60,
+            "hour" => 3600,
+            "day" => 86400,
+            "week" => 7 * 86400,
+            "month" => 30 * 86400,
+            "year" => 365 * 86400,
+            _ => return Err(invalid()),
+        };
+        return Ok(now - count * seconds);
+    }
+
+    let (date, time) = value.split_once([' ', 't']).unwrap_or((&value, "00:00:00"));
+    let numbers = |text: &str, sep: char| -> Option<Vec<i64>> { text.split(sep).map(|n| n.parse().ok()).collect() };
+    let date = numbers(date, '-').filter(|d| d.len() == 3).ok_or_else(invalid)?;
+    let time = numbers(time, ':').filter(|t| (2..=3).contains(&t.len())).ok_or_else(invalid)?;
+    if !(1..=12).contains(&date[1]) || !(1..=31).contains(&date[2]) {
+        return Err(invalid());
+    }
+    let days = days_from_civil(date[0], date[1], date[2]);
+    Ok(days * 86400 + time[0] * 3600 + time[1] * 60 + time.get(2).copied().unwrap_or(0))
+}
+
+/// Parses a date for a commit (`--date`, `RIT_AUTHOR_DATE`) into a
+/// timestamp and timezone. Besides what [`parse_date`] accepts, this takes
+/// Git's internal `<unix> <+hhmm>` form and `@<unix>`, and dates may end in
+/// a `+hhmm` zone; dates without one are in local time.
+pub(crate) fn parse_signature_date(value: &str) -> Result<(i64, String), String> {
+    let value = value.trim();
+    let (date, zone) = match value.rsplit_once(' ') {
+        Some((date, zone)) if zone.len() == 5 && zone.starts_with(['+', '-']) && zone[1..].bytes().all(|b| b.is_ascii_digit()) => {
+            (date.trim(), Some(zone))
+        }
+        _ => (value, None),
+    };
+    let date = date.strip_prefix('@').unwrap_or(date);
+    let unix = date.parse::<i64>().ok();
+    let timestamp = unix.map_or_else(|| parse_date(date), Ok)?;
+
+    // A calendar date is wall-clock time in its zone; anything relative or
+    // a plain Unix time is already absolute
+    let wall_clock = unix.is_none() && date.starts_with(|c: char| c.is_ascii_digit());
+    let offset = match zone {
+        Some(zone) => {
+            let sign = if zone.starts_with('-') { -1 } else { 1 };
+            sign * (zone[1..3].parse::<i64>().unwrap_or(0) * 3600 + zone[3..].parse::<i64>().unwrap_or(0) * 60)
+        }
+        None if wall_clock => local_offset(timestamp - local_offset(timestamp)),
+        None => local_offset(timestamp),
+    };
+    let timestamp = if wall_clock { timestamp - offset } else { timestamp };
+    Ok((timestamp, format_offset(offset)))
+}
+
+/// Converts a (year, month, day) date into days since 1970-01-01.
+pub(crate) fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let day_of_year = (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// Converts days since 1970-01-01 into a (year, month, day) date.
+pub(crate) fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    // Howard Hinnant's algorithm, counting in 400-year eras from 0000-03-01
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// The local timezone's offset from UTC in seconds at `timestamp`, from
+/// `$TZ` or the system's zone. Anything that can't be read counts as UTC.
+pub(crate) fn local_offset(timestamp: i64) -> i64 {
+    offset_in(&TimeZone::system(), timestamp)
+}
+
+/// The offset from UTC in seconds that `zone` has at `timestamp`.
+fn offset_in(zone: &TimeZone, timestamp: i64) -> i64 {
+    Timestamp::from_second(timestamp).map_or(0, |timestamp| i64::from(zone.to_offset(timestamp).seconds()))
+}
+
+/// Formats an offset in seconds as a timezone such as `+0530`.
+pub(crate) fn format_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let minutes = offset.abs() / 60;
+    format!("{}{:02}{:02}", sign, minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A version 1 zoneinfo file: `times` switch to the type at the same
+    /// place in `indices`, and `offsets` are the types' UTC offsets.
+    fn tzif_v1(times: &[i32], indices: &[u8], offsets: &[i32]) -> Vec<u8> {
+        let mut data = b"TZif".to_vec();
+        data.push(0);
+        data.extend([0; 15]);
+        for count in [0, 0, 0, times.len(), offsets.len(), 4] {
+            data.extend((count as u32).to_be_bytes());
+        }
+        for &time in times {
+            data.extend(time.to_be_bytes());
+        }
+        data.extend(indices);
+        for &offset in offsets {
+            data.extend(offset.to_be_bytes());
+            data.extend([0, 0]);
+        }
+        data.extend(b"ZZZ\0");
+        data
+    }
+
+    #[test]
+    fn formats_offsets() {
+        assert_eq!(format_offset(0), "+0000");
+        assert_eq!(format_offset(19800), "+0530");
+        assert_eq!(format_offset(-18000), "-0500");
+        assert_eq!(format_offset(-1800), "-0030");
+    }
+
+    #[test]
+    fn offsets_switch_at_dst_boundaries() {
+        // 2024-03-31 01:00 UTC and 2024-10-27 01:00 UTC
+        let cet = TimeZone::posix("CET-1CEST,M3.5.0,M10.5.0/3").unwrap();
+        let (start, end) = (1_711_846_800, 1_729_990_800);
+        assert_eq!(offset_in(&cet, start - 1), 3600);
+        assert_eq!(offset_in(&cet, start), 7200);
+        assert_eq!(offset_in(&cet, end - 1), 7200);
+        assert_eq!(offset_in(&cet, end), 3600);
+    }
+
+    #[test]
+    fn offsets_in_the_southern_hemisphere() {
+        // Daylight saving ends 2024-04-06 16:00 UTC, starts 2024-10-05 16:00 UTC
+        let sydney = TimeZone::posix("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        let (end, start) = (1_712_419_200, 1_728_144_000);
+        assert_eq!(offset_in(&sydney, 1_704_067_200), 39600);
+        assert_eq!(offset_in(&sydney, end - 1), 39600);
+        assert_eq!(offset_in(&sydney, end), 36000);
+        assert_eq!(offset_in(&sydney, start - 1), 36000);
+        assert_eq!(offset_in(&sydney, start), 39600);
+    }
+
+    #[test]
+    fn offsets_from_version_1_zone_files() {
+        let zone = TimeZone::tzif("Test/Zone", &tzif_v1(&[1_000_000_000], &[1], &[0, 3600])).unwrap();
+        assert_eq!(offset_in(&zone, 999_999_999), 0);
+        assert_eq!(offset_in(&zone, 1_000_000_000), 3600);
+        assert_eq!(offset_in(&zone, 1_711_846_800), 3600);
+    }
+
+    #[test]
+    fn calendar_round_trips() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2024, 2, 29), 19_782);
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+        assert_eq!(civil_from_days(-1), (1969, 12, 31));
+    }
+
+    #[test]
+    fn parses_absolute_dates() {
+        assert_eq!(parse_date("2024-01-31"), Ok(1_706_659_200));
+        assert_eq!(parse_date("2024-01-31 12:30"), Ok(1_706_704_200));
+        assert_eq!(parse_date("1700000000"), Ok(1_700_000_000));
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("3 fortnights ago").is_err());
+    }
+
+    #[test]
+    fn parses_signature_dates_with_zones() {
+        assert_eq!(parse_signature_date("1700000000 +0200"), Ok((1_700_000_000, "+0200".to_string())));
+        assert_eq!(parse_signature_date("@1700000000 -0130"), Ok((1_700_000_000, "-0130".to_string())));
+        // Calendar dates are wall-clock time in their zone
+        assert_eq!(parse_signature_date("2024-01-31 12:00:00 +0100"), Ok((1_706_698_800, "+0100".to_string())));
+        assert!(parse_signature_date("someday +0100").is_err());
+    }
+}
@@ -0,0 +1,104 @@
+use super::diff::{collect_changes, DiffOptions};
+use super::merge::ancestors;
+use super::revision::resolve_commit;
+use super::tag::{read_tag, tagged_commit};
+use super::utils::{find_repo_root, list_refs, object_type};
+use std::cmp::Reverse;
+use std::io;
+use std::path::Path;
+
+/// Options for `rit describe`.
+#[derive(Debug, Default)]
+pub struct DescribeOptions {
+    /// The commit to describe; defaults to HEAD
+    pub revision: Option<String>,
+    /// Consider lightweight tags as well as annotated ones
+    pub tags: bool,
+    /// Always print the `-<n>-g<hash>` suffix, even on a tagged commit
+    pub long: bool,
+    /// Suffix to append when the working tree differs from HEAD
+    pub dirty: Option<String>,
+    /// Fall back to the abbreviated hash when no tag can describe the commit
+    pub always: bool,
+}
+
+/// A tag that can describe the commit, and how far the commit is from it.
+struct Candidate {
+    name: String,
+    depth: usize,
+    annotated: bool,
+    tagged_at: i64,
+}
+
+pub fn run(options: &DescribeOptions) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    if options.dirty.is_some() && options.revision.is_some() {
+        return Err(io::Error::other("--dirty is incompatible with commit-ishes"));
+    }
+
+    let revision = options.revision.as_deref().unwrap_or("HEAD");
+    let commit = resolve_commit(&repo_path, revision)?;
+    let mut description = describe(&repo_path, &commit, options)?;
+
+    if let Some(mark) = &options.dirty
+        && is_dirty(&repo_path)?
+    {
+        description.push_str(mark);
+    }
+    println!("{}", description);
+    Ok(())
+}
+
+fn describe(repo_path: &Path, commit: &str, options: &DescribeOptions) -> io::Result<String> {
+    let history = ancestors(repo_path, commit)?;
+    let mut candidates = Vec::new();
+    let mut skipped_lightweight = false;
+
+    for (name, hash) in list_refs(repo_path, "tags")? {
+        let annotated = object_type(repo_path, &hash)? == "tag";
+        if !annotated && !options.tags {
+            skipped_lightweight = true;
+            continue;
+        }
+        let Some(tagged) = tagged_commit(repo_path, &hash) else {
+            continue;
+        };
+        if !history.contains(&tagged) {
+            continue;
+        }
+
+        // Commits reachable from the described commit but not from the tag
+        let tag_history = ancestors(repo_path, &tagged)?;
+        let depth = history.iter().filter(|hash| !tag_history.contains(*hash)).count();
+        let tagged_at = match annotated {
+            true => read_tag(repo_path, &hash)?.tagger.map_or(0, |tagger| tagger.timestamp),
+            false => 0,
+        };
+        candidates.push(Candidate { name, depth, annotated, tagged_at });
+    }
+
+    // The closest tag wins; on a tie, annotated tags beat lightweight ones
+    // and newer tags beat older ones
+    let best = candidates
+        .into_iter()
+        .min_by_key(|c| (c.depth, !c.annotated, Reverse(c.tagged_at), c.name.clone()));
+
+    let short = &commit[..7];
+    match best {
+        Some(tag) if tag.depth == 0 && !options.long => Ok(tag.name),
+        Some(tag) => Ok(format!("{}-{}-g{}", tag.name, tag.depth, short)),
+        None if options.always => Ok(short.to_string()),
+        None if skipped_lightweight => Err(io::Error::other(format!(
+            "No annotated tags can describe '{}'.\nHowever, there were unannotated tags: try --tags.",
+            commit
+        ))),
+        None => Err(io::Error::other(format!("No names found, cannot describe '{}'.", commit))),
+    }
+}
+
+/// Whether tracked files differ from HEAD, either staged or in the working tree.
+fn is_dirty(repo_path: &Path) -> io::Result<bool> {
+    let unstaged = collect_changes(repo_path, None, &DiffOptions::default())?;
+    let staged = collect_changes(repo_path, None, &DiffOptions { cached: true, ..Default::default() })?;
+    Ok(!unstaged.is_empty() || !staged.is_empty())
+}
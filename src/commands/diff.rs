@@ -1,74 +1,1043 @@
 // Create a new file: src/commands/diff.rs
 use super::commit::read_index;
-use super::utils::{find_repo_root, read_object};
-use std::fs;
+use super::config::read_config_file;
+use super::utils::{find_repo_root, get_tree_hash, is_binary, read_head_commit, read_object, read_tree_entries, read_worktree_file};
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
 use std::io;
+use std::path::{Path, PathBuf};
 use colored::Colorize;
+use flate2::{Compression, write::ZlibEncoder};
+use regex::Regex;
+use std::io::Write;
 
-pub fn run(path: Option<&str>) -> io::Result<()> {
+/// By default a "word" is any run of non-whitespace characters.
+const DEFAULT_WORD_REGEX: &str = r"\S+";
+
+/// Default minimum similarity for `-M` / `-C` without an explicit value.
+pub const DEFAULT_SIMILARITY: u8 = 50;
+
+/// Git only treats a block as moved once it has at least this many
+/// alphanumeric characters, so single braces or blank lines don't light up.
+const MOVED_BLOCK_MIN_ALNUM: usize = 20;
+
+/// How `--word-diff` marks changed words.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum WordDiffMode {
+    /// Highlight changed words using only colors
+    Color,
+    /// Wrap changes in [-removed-] and {+added+} markers
+    Plain,
+    /// Line-based format for scripts, one token run per line
+    Porcelain,
+}
+
+/// Which summaries to print instead of (or alongside) the full patch.
+#[derive(Debug, Default, Clone)]
+pub struct DiffOptions {
+    pub stat: bool,
+    pub numstat: bool,
+    pub shortstat: bool,
+    pub name_only: bool,
+    pub name_status: bool,
+    /// Emit base85-encoded binary patches instead of "Binary files differ"
+    pub binary: bool,
+    /// Diff words instead of lines
+    pub word_diff: Option<WordDiffMode>,
+    /// Overrides `diff.<ext>.wordRegex` / `diff.wordRegex`
+    pub word_regex: Option<String>,
+    /// Highlight blocks of lines that were moved rather than changed
+    pub color_moved: bool,
+    /// Minimum similarity (percent) for pairing deletions with additions
+    pub find_renames: Option<u8>,
+    /// Minimum similarity (percent) for reporting additions as copies
+    pub find_copies: Option<u8>,
+    /// Compare the index against HEAD instead of the working tree
+    pub cached: bool,
+    /// Print the patch even when a summary mode was requested
+    pub patch: bool,
+}
+
+impl DiffOptions {
+    /// The full patch is printed when asked for or when no summary mode was
+    /// requested.
+    fn show_patch(&self) -> bool {
+        self.patch || !(self.stat || self.numstat || self.shortstat || self.name_only || self.name_status)
+    }
+}
+
+/// How a file differs between the two sides of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ChangeKind {
+    Added,
+    Modified,
+    Deleted,
+    /// Renamed from `FileChange::old_path`, with the similarity score
+    Renamed(u8),
+    /// Copied from `FileChange::old_path`, with the similarity score
+    Copied(u8),
+}
+
+impl ChangeKind {
+    /// The status letter used by `--name-status`, e.g. `M` or `R087`.
+    pub fn letter(self) -> String {
+        match self {
+            ChangeKind::Added => "A".to_string(),
+            ChangeKind::Modified => "M".to_string(),
+            ChangeKind::Deleted => "D".to_string(),
+            ChangeKind::Renamed(score) => format!("R{:03}", score),
+            ChangeKind::Copied(score) => format!("C{:03}", score),
+        }
+    }
+}
+
+/// A single changed file, with the contents of both sides (if present).
+pub(crate) struct FileChange {
+    pub path: PathBuf,
+    /// Where a renamed or copied file came from
+    pub old_path: Option<PathBuf>,
+    pub kind: ChangeKind,
+    pub old: Option<Vec<u8>>,
+    pub new: Option<Vec<u8>>,
+}
+
+impl FileChange {
+    fn new(path: PathBuf, kind: ChangeKind, old: Option<Vec<u8>>, new: Option<Vec<u8>>) -> Self {
+        FileChange { path, old_path: None, kind, old, new }
+    }
+
+    /// The path as shown in summaries: `old => new` for renames and copies.
+    fn display_path(&self) -> String {
+        match &self.old_path {
+            Some(old_path) => format!("{} => {}", old_path.display(), self.path.display()),
+            None => self.path.display().to_string(),
+        }
+    }
+
+    /// A change is binary if either side looks like binary content.
+    fn is_binary(&self) -> bool {
+        self.old.as_deref().is_some_and(is_binary) || self.new.as_deref().is_some_and(is_binary)
+    }
+
+    fn old_text(&self) -> String {
+        String::from_utf8_lossy(self.old.as_deref().unwrap_or_default()).into_owned()
+    }
+
+    fn new_text(&self) -> String {
+        String::from_utf8_lossy(self.new.as_deref().unwrap_or_default()).into_owned()
+    }
+
+    /// Count (added, removed) lines. Binary files count as no lines.
+    fn line_counts(&self) -> (usize, usize) {
+        if self.is_binary() {
+            return (0, 0);
+        }
+        let old = self.old_text();
+        let new = self.new_text();
+        let mut added = 0;
+        let mut removed = 0;
+        for line in diff_lines(&old, &new) {
+            match line {
+                DiffLine::Insert(_) => added += 1,
+                DiffLine::Delete(_) => removed += 1,
+                DiffLine::Equal(_) => {}
+            }
+        }
+        (added, removed)
+    }
+}
+
+/// One line (or, for word diffs, one token) of a diff.
+pub(crate) enum DiffLine<'a> {
+    Equal(&'a str),
+    Delete(&'a str),
+    Insert(&'a str),
+}
+
+/// Computes a line-level diff. Files with more distinct lines than can be
+/// encoded for `dissimilar` are shown as replaced outright.
+pub(crate) fn diff_lines<'a>(old: &'a str, new: &'a str) -> Vec<DiffLine<'a>> {
+    let a: Vec<&str> = old.lines().collect();
+    let b: Vec<&str> = new.lines().collect();
+
+    // Common prefix and suffix never need to go through the search.
+    let prefix = a.iter().zip(&b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+
+    let mut result: Vec<DiffLine> = a[..prefix].iter().map(|l| DiffLine::Equal(l)).collect();
+    match diff_sequences(a_mid, b_mid) {
+        Some(edits) => result.extend(edits.into_iter().map(|(edit, line)| edit.wrap(line))),
+        None => {
+            result.extend(a_mid.iter().map(|l| DiffLine::Delete(l)));
+            result.extend(b_mid.iter().map(|l| DiffLine::Insert(l)));
+        }
+    }
+    result.extend(a[a.len() - suffix..].iter().map(|l| DiffLine::Equal(l)));
+    result
+}
+
+/// What happens to one item of a sequence in a diff.
+#[derive(Clone, Copy)]
+enum Edit {
+    Equal,
+    Delete,
+    Insert,
+}
+
+impl Edit {
+    fn wrap(self, text: &str) -> DiffLine<'_> {
+        match self {
+            Edit::Equal => DiffLine::Equal(text),
+            Edit::Delete => DiffLine::Delete(text),
+            Edit::Insert => DiffLine::Insert(text),
+        }
+    }
+}
+
+/// The most distinct items `diff_sequences` can encode: one character
+/// each, from U+10000 up to the last scalar value U+10FFFF.
+const MAX_DIFF_UNITS: usize = 0x100000;
+
+/// Diffs two sequences by mapping every distinct item to a single
+/// character and letting `dissimilar` diff the resulting strings, which
+/// keeps memory linear in the input. Returns `None` if there are too many
+/// distinct items to encode.
+fn diff_sequences<T: Copy + Eq + Hash>(old: &[T], new: &[T]) -> Option<Vec<(Edit, T)>> {
+    let mut ids: HashMap<T, u32> = HashMap::new();
+    let mut items: Vec<T> = Vec::new();
+
+    let mut encode = |sequence: &[T]| -> Option<String> {
+        sequence
+            .iter()
+            .map(|&item| {
+                let id = match ids.get(&item) {
+                    Some(&id) => id,
+                    None if items.len() >= MAX_DIFF_UNITS => return None,
+                    None => {
+                        items.push(item);
+                        ids.insert(item, items.len() as u32 - 1);
+                        items.len() as u32 - 1
+                    }
+                };
+                // Start above the BMP so every id maps to a valid scalar value.
+                char::from_u32(0x10000 + id)
+            })
+            .collect()
+    };
+    let old_encoded = encode(old)?;
+    // Ids are handed out in order, so the old side holds exactly the ids below this
+    let old_items = old_encoded.chars().map(|c| c as u32 - 0x10000 + 1).max().unwrap_or(0);
+    let new_encoded = encode(new)?;
+
+    // Nothing in common (such as a full rewrite): skip the search entirely
+    if new_encoded.chars().all(|c| c as u32 - 0x10000 >= old_items) {
+        let deleted = old.iter().map(|&item| (Edit::Delete, item));
+        return Some(deleted.chain(new.iter().map(|&item| (Edit::Insert, item))).collect());
+    }
+
+    let mut result = Vec::new();
+    for chunk in dissimilar::diff(&old_encoded, &new_encoded) {
+        let (text, edit) = match chunk {
+            dissimilar::Chunk::Equal(text) => (text, Edit::Equal),
+            dissimilar::Chunk::Delete(text) => (text, Edit::Delete),
+            dissimilar::Chunk::Insert(text) => (text, Edit::Insert),
+        };
+        result.extend(text.chars().map(|c| (edit, items[(c as u32 - 0x10000) as usize])));
+    }
+    Some(result)
+}
+
+pub fn run(path: Option<&str>, options: &DiffOptions) -> io::Result<()> {
     let repo_root = find_repo_root()?;
+    let changes = collect_changes(&repo_root, path, options)?;
+    print_changes(&repo_root, &changes, options)
+}
+
+/// Collects the changes `rit diff` reports: the working tree against the
+/// index, or with `cached` the index against HEAD.
+pub(crate) fn collect_changes(repo_root: &Path, path: Option<&str>, options: &DiffOptions) -> io::Result<Vec<FileChange>> {
+    let mut changes = if options.cached {
+        // Staged changes: HEAD's tree against the index
+        let index: FileMap = read_index(repo_root)?
+            .into_iter()
+            .map(|entry| (entry.path, (entry.mode, entry.sha1)))
+            .collect();
+        changes_between(repo_root, &head_tree_map(repo_root)?, &index)?
+    } else {
+        working_tree_changes(repo_root, path)?
+    };
+
+    changes = detect_renames(changes, options.find_renames, options.find_copies);
+    if let Some(filter) = path {
+        let filter = Path::new(filter);
+        changes.retain(|c| {
+            c.path.starts_with(filter) || c.old_path.as_ref().is_some_and(|p| p.starts_with(filter))
+        });
+    }
+    Ok(changes)
+}
+
+/// Collects every tracked file whose working copy differs from the staged blob.
+fn working_tree_changes(repo_root: &Path, path: Option<&str>) -> io::Result<Vec<FileChange>> {
     let work_dir = repo_root.parent().unwrap();
-    let index_entries = read_index(&repo_root)?;
+    let index_entries = read_index(repo_root)?;
 
     // 1. Create a HashMap for easy lookup of staged files (path -> sha1)
-    let mut staged_files = std::collections::HashMap::new();
+    let mut staged_files = HashMap::new();
     for entry in index_entries {
         staged_files.insert(entry.path, entry.sha1);
     }
 
     // 2. Determine which files to diff (all tracked files or just one)
-    let files_to_diff: Vec<_> = if let Some(file_path) = path {
-        vec![std::path::PathBuf::from(file_path)]
+    let mut files_to_diff: Vec<_> = if let Some(file_path) = path {
+        vec![PathBuf::from(file_path)]
     } else {
         staged_files.keys().cloned().collect()
     };
+    files_to_diff.sort();
 
-    // 3. For each file, generate and print the diff
+    // 3. Compare each staged blob with the file on disk
+    let mut changes = Vec::new();
     for file_path in files_to_diff {
         if let Some(sha1) = staged_files.get(&file_path) {
-            // Read the staged version (blob object)
-            let staged_content_bytes = read_object(&repo_root, sha1)?;
-            let staged_content = String::from_utf8_lossy(&staged_content_bytes);
-
-            // Read the working directory version
+            let staged_content = read_object(repo_root, sha1)?;
             let working_path = work_dir.join(&file_path);
-            let working_content = fs::read_to_string(working_path).unwrap_or_default();
-
-            // Compare and print the diff if there are changes
-            if staged_content != working_content {
-                println!(
-                    "diff --rit a/{} b/{}",
-                    file_path.display(),
-                    file_path.display()
-                );
-
-                let diff = dissimilar::diff(&staged_content, &working_content);
-
-                for chunk in diff {
-                    match chunk {
-                        dissimilar::Chunk::Equal(text) => {
-                            // Print lines that are the same, prefixed with a space
-                            for line in text.lines() {
-                                println!(" {}", line);
-                            }
-                        }
-                        dissimilar::Chunk::Delete(text) => {
-                            // Print deleted lines in red, prefixed with a '-'
-                            for line in text.lines() {
-                                println!("{}", format!("-{}", line).red());
-                            }
-                        }
-                        dissimilar::Chunk::Insert(text) => {
-                            // Print added lines in green, prefixed with a '+'
-                            for line in text.lines() {
-                                println!("{}", format!("+{}", line).green());
-                            }
-                        }
+
+            let change = if let Some((_, working_content)) = read_worktree_file(&working_path)? {
+                if working_content == staged_content {
+                    continue;
+                }
+                FileChange::new(file_path, ChangeKind::Modified, Some(staged_content), Some(working_content))
+            } else {
+                FileChange::new(file_path, ChangeKind::Deleted, Some(staged_content), None)
+            };
+            changes.push(change);
+        }
+    }
+    Ok(changes)
+}
+
+/// A flattened snapshot (a tree or the index): path -> (mode, sha1).
+pub(crate) type FileMap = HashMap<PathBuf, (String, String)>;
+
+/// The flattened tree of the HEAD commit, or an empty map before the first commit.
+pub(crate) fn head_tree_map(repo_root: &Path) -> io::Result<FileMap> {
+    match read_head_commit(repo_root)? {
+        Some(commit) if !commit.is_empty() => {
+            read_tree_entries(repo_root, &get_tree_hash(repo_root, &commit)?)
+        }
+        _ => Ok(FileMap::new()),
+    }
+}
+
+/// Compares two snapshots and loads the contents of every file that differs.
+pub(crate) fn changes_between(repo_root: &Path, old: &FileMap, new: &FileMap) -> io::Result<Vec<FileChange>> {
+    let mut paths: Vec<&PathBuf> = old.keys().chain(new.keys().filter(|p| !old.contains_key(*p))).collect();
+    paths.sort();
+
+    let mut changes = Vec::new();
+    for path in paths {
+        let change = match (old.get(path), new.get(path)) {
+            (Some(o), Some(n)) if o.1 == n.1 => continue,
+            (Some(o), Some(n)) => FileChange::new(
+                path.clone(),
+                ChangeKind::Modified,
+                Some(read_object(repo_root, &o.1)?),
+                Some(read_object(repo_root, &n.1)?),
+            ),
+            (Some(o), None) => FileChange::new(path.clone(), ChangeKind::Deleted, Some(read_object(repo_root, &o.1)?), None),
+            (None, Some(n)) => FileChange::new(path.clone(), ChangeKind::Added, None, Some(read_object(repo_root, &n.1)?)),
+            (None, None) => continue,
+        };
+        changes.push(change);
+    }
+    Ok(changes)
+}
+
+/// Parses a `-M` / `-C` similarity: `75%` is a percentage, while bare digits
+/// are a fraction like Git's (`5` is 50%, `05` is 5%).
+pub fn parse_similarity(value: &str) -> Result<u8, String> {
+    let invalid = || format!("invalid similarity '{}'", value);
+    let percent = if let Some(number) = value.strip_suffix('%') {
+        number.parse::<u32>().map_err(|_| invalid())?
+    } else {
+        if value.is_empty() || !value.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let digits = &value[..value.len().min(6)];
+        (digits.parse::<u64>().map_err(|_| invalid())? * 100 / 10u64.pow(digits.len() as u32)) as u32
+    };
+    if percent > 100 {
+        return Err(invalid());
+    }
+    Ok(percent as u8)
+}
+
+/// How much of two files' content is shared, as a percentage of the larger one.
+fn similarity(old: &[u8], new: &[u8]) -> u8 {
+    if old == new {
+        return 100;
+    }
+    if is_binary(old) || is_binary(new) {
+        return 0;
+    }
+    let old_text = String::from_utf8_lossy(old);
+    let new_text = String::from_utf8_lossy(new);
+    let common: usize = diff_lines(&old_text, &new_text)
+        .iter()
+        .map(|line| match line {
+            DiffLine::Equal(text) => text.len() + 1,
+            _ => 0,
+        })
+        .sum();
+    let larger = old.len().max(new.len());
+    (common * 100 / larger).min(99) as u8
+}
+
+/// Pairs up deleted and added files into renames (and, with `copies`, turns
+/// additions that resemble a modified or deleted file into copies).
+pub(crate) fn detect_renames(changes: Vec<FileChange>, renames: Option<u8>, copies: Option<u8>) -> Vec<FileChange> {
+    // Copy detection implies rename detection, like in Git
+    let Some(rename_threshold) = renames.or(copies) else {
+        return changes;
+    };
+
+    let (mut added, rest): (Vec<FileChange>, Vec<FileChange>) =
+        changes.into_iter().partition(|c| c.kind == ChangeKind::Added);
+    let (deleted, mut result): (Vec<FileChange>, Vec<FileChange>) =
+        rest.into_iter().partition(|c| c.kind == ChangeKind::Deleted);
+
+    // Score every deleted/added pair and hand out the best matches first
+    let mut candidates = Vec::new();
+    for (d, source) in deleted.iter().enumerate() {
+        for (a, target) in added.iter().enumerate() {
+            let score = similarity(source.old.as_deref().unwrap_or_default(), target.new.as_deref().unwrap_or_default());
+            if score >= rename_threshold {
+                candidates.push((score, d, a));
+            }
+        }
+    }
+    candidates.sort_by_key(|&(score, _, _)| std::cmp::Reverse(score));
+
+    let mut deleted_used = vec![false; deleted.len()];
+    for (score, d, a) in candidates {
+        if deleted_used[d] || added[a].kind != ChangeKind::Added {
+            continue;
+        }
+        deleted_used[d] = true;
+        added[a].kind = ChangeKind::Renamed(score);
+        added[a].old_path = Some(deleted[d].path.clone());
+        added[a].old = deleted[d].old.clone();
+    }
+
+    // Remaining additions may be copies of a modified or deleted file
+    if let Some(copy_threshold) = copies {
+        let sources: Vec<&FileChange> = result
+            .iter()
+            .filter(|c| c.kind == ChangeKind::Modified)
+            .chain(deleted.iter())
+            .collect();
+        for target in added.iter_mut().filter(|c| c.kind == ChangeKind::Added) {
+            let new = target.new.as_deref().unwrap_or_default();
+            let best = sources
+                .iter()
+                .map(|source| (similarity(source.old.as_deref().unwrap_or_default(), new), source))
+                .filter(|(score, _)| *score >= copy_threshold)
+                .max_by_key(|(score, _)| *score);
+            if let Some((score, source)) = best {
+                target.kind = ChangeKind::Copied(score);
+                target.old_path = Some(source.path.clone());
+                target.old = source.old.clone();
+            }
+        }
+    }
+
+    result.extend(deleted.into_iter().zip(deleted_used).filter(|(_, used)| !used).map(|(c, _)| c));
+    result.extend(added);
+    result.sort_by(|a, b| a.path.cmp(&b.path));
+    result
+}
+
+/// Prints a set of changes in every format requested by `options`.
+pub(crate) fn print_changes(
+    repo_root: &Path,
+    changes: &[FileChange],
+    options: &DiffOptions,
+) -> io::Result<()> {
+    write_changes(&mut io::stdout().lock(), repo_root, changes, options)
+}
+
+/// Like [`print_changes`], but writes to `out` so callers such as
+/// `log --graph` can decorate each line.
+pub(crate) fn write_changes(
+    out: &mut dyn Write,
+    repo_root: &Path,
+    changes: &[FileChange],
+    options: &DiffOptions,
+) -> io::Result<()> {
+    if options.name_only {
+        for change in changes {
+            writeln!(out, "{}", change.path.display())?;
+        }
+    }
+    if options.name_status {
+        for change in changes {
+            match &change.old_path {
+                Some(old_path) => writeln!(
+                    out,
+                    "{}\t{}\t{}",
+                    change.kind.letter(),
+                    old_path.display(),
+                    change.path.display()
+                )?,
+                None => writeln!(out, "{}\t{}", change.kind.letter(), change.path.display())?,
+            }
+        }
+    }
+    if options.numstat {
+        for change in changes {
+            if change.is_binary() {
+                writeln!(out, "-\t-\t{}", change.display_path())?;
+            } else {
+                let (added, removed) = change.line_counts();
+                writeln!(out, "{}\t{}\t{}", added, removed, change.display_path())?;
+            }
+        }
+    }
+    if options.stat {
+        print_stat(out, changes)?;
+    } else if options.shortstat {
+        let counts: Vec<(usize, usize)> = changes.iter().map(FileChange::line_counts).collect();
+        print_totals(out, &counts)?;
+    }
+    if options.show_patch() {
+        let config = read_config_file(repo_root)?;
+        let moved = options.color_moved.then(|| MovedLines::collect(changes));
+        for change in changes {
+            match options.word_diff {
+                Some(mode) if !change.is_binary() => {
+                    let regex = word_regex(&config, &change.path, options)?;
+                    print_word_diff(out, change, mode, &regex)?;
+                }
+                _ => print_patch(out, change, options, moved.as_ref())?,
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Prints a `--stat` histogram, scaling the bars to fit the terminal width.
+fn print_stat(out: &mut dyn Write, changes: &[FileChange]) -> io::Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let counts: Vec<(usize, usize)> = changes.iter().map(FileChange::line_counts).collect();
+    let names: Vec<String> = changes.iter().map(FileChange::display_path).collect();
+
+    let name_width = names.iter().map(|n| n.chars().count()).max().unwrap_or(0);
+    let max_changes = counts.iter().map(|(a, r)| a + r).max().unwrap_or(0);
+    let count_width = max_changes.to_string().len();
+
+    // " <name> | <count> <bars>"
+    let terminal_width = terminal_width();
+    let bar_room = terminal_width
+        .saturating_sub(name_width + count_width + 5)
+        .max(10);
+
+    for ((name, (added, removed)), change) in names.iter().zip(&counts).zip(changes) {
+        if change.is_binary() {
+            let size = |side: &Option<Vec<u8>>| side.as_ref().map_or(0, Vec::len);
+            writeln!(
+                out,
+                " {:<name_width$} | Bin {} -> {} bytes",
+                name,
+                size(&change.old),
+                size(&change.new)
+            )?;
+            continue;
+        }
+        let total = added + removed;
+        let (mut plus, mut minus) = (*added, *removed);
+        if max_changes > bar_room {
+            // Scale, but never hide a side that has at least one change.
+            plus = scale(*added, max_changes, bar_room);
+            minus = scale(*removed, max_changes, bar_room);
+        }
+        writeln!(
+            out,
+            " {:<name_width$} | {:>count_width$} {}{}",
+            name,
+            total,
+            "+".repeat(plus).green(),
+            "-".repeat(minus).red(),
+        )?;
+    }
+    print_totals(out, &counts)
+}
+
+fn scale(value: usize, max: usize, width: usize) -> usize {
+    if value == 0 {
+        0
+    } else {
+        (value * width / max).max(1)
+    }
+}
+
+/// Width of the terminal, taken from `$COLUMNS` with a sensible default.
+fn terminal_width() -> usize {
+    std::env::var("COLUMNS")
+        .ok()
+        .and_then(|c| c.parse().ok())
+        .unwrap_or(80)
+}
+
+/// Prints the `N files changed, X insertions(+), Y deletions(-)` summary line.
+fn print_totals(out: &mut dyn Write, counts: &[(usize, usize)]) -> io::Result<()> {
+    if counts.is_empty() {
+        return Ok(());
+    }
+    let added: usize = counts.iter().map(|(a, _)| a).sum();
+    let removed: usize = counts.iter().map(|(_, r)| r).sum();
+
+    let plural = |n: usize, one: &'static str, many: &'static str| if n == 1 { one } else { many };
+
+    let mut summary = format!(
+        " {} {} changed",
+        counts.len(),
+        plural(counts.len(), "file", "files")
+    );
+    if added > 0 || removed == 0 {
+        summary.push_str(&format!(", {} {}(+)", added, plural(added, "insertion", "insertions")));
+    }
+    if removed > 0 || added == 0 {
+        summary.push_str(&format!(", {} {}(-)", removed, plural(removed, "deletion", "deletions")));
+    }
+    writeln!(out, "{}", summary)
+}
+
+fn print_header(out: &mut dyn Write, change: &FileChange) -> io::Result<()> {
+    let old_path = change.old_path.as_ref().unwrap_or(&change.path);
+    writeln!(
+        out,
+        "diff --rit a/{} b/{}",
+        old_path.display(),
+        change.path.display()
+    )?;
+    let (verb, score) = match change.kind {
+        ChangeKind::Renamed(score) => ("rename", score),
+        ChangeKind::Copied(score) => ("copy", score),
+        _ => return Ok(()),
+    };
+    writeln!(out, "similarity index {}%", score)?;
+    writeln!(out, "{} from {}", verb, old_path.display())?;
+    writeln!(out, "{} to {}", verb, change.path.display())
+}
+
+/// Prints the full line-by-line patch for one file.
+fn print_patch(out: &mut dyn Write, change: &FileChange, options: &DiffOptions, moved: Option<&MovedLines>) -> io::Result<()> {
+    print_header(out, change)?;
+
+    if change.is_binary() {
+        if options.binary {
+            print_binary_patch(out, change)?;
+        } else {
+            let old_path = change.old_path.as_ref().unwrap_or(&change.path);
+            let side = |content: &Option<Vec<u8>>, prefix: &str, path: &Path| match content {
+                Some(_) => format!("{}/{}", prefix, path.display()),
+                None => "/dev/null".to_string(),
+            };
+            writeln!(
+                out,
+                "Binary files {} and {} differ",
+                side(&change.old, "a", old_path),
+                side(&change.new, "b", &change.path)
+            )?;
+        }
+        return Ok(());
+    }
+
+    // A pure rename or copy has no content changes to show
+    if change.old == change.new {
+        return Ok(());
+    }
+
+    let old = change.old_text();
+    let new = change.new_text();
+    let lines = diff_lines(&old, &new);
+    let is_moved = match moved {
+        Some(moved) => moved.mark(&lines),
+        None => vec![false; lines.len()],
+    };
+
+    for (line, is_moved) in lines.iter().zip(is_moved) {
+        match line {
+            // Print lines that are the same, prefixed with a space
+            DiffLine::Equal(text) => writeln!(out, " {}", text)?,
+            // Moved lines get their own colors so they stand out from real edits
+            DiffLine::Delete(text) if is_moved => writeln!(out, "{}", format!("-{}", text).magenta().bold())?,
+            DiffLine::Insert(text) if is_moved => writeln!(out, "{}", format!("+{}", text).cyan().bold())?,
+            // Print deleted lines in red, prefixed with a '-'
+            DiffLine::Delete(text) => writeln!(out, "{}", format!("-{}", text).red())?,
+            // Print added lines in green, prefixed with a '+'
+            DiffLine::Insert(text) => writeln!(out, "{}", format!("+{}", text).green())?,
+        }
+    }
+    Ok(())
+}
+
+/// Prints a merge's combined diff for one file. Each line gets one column
+/// per parent, holding `+` if the line is new relative to that parent or `-`
+/// if it was removed from it.
+pub(crate) fn print_combined_diff(path: &Path, parents: &[Option<Vec<u8>>], result: Option<&[u8]>) {
+    println!("diff --cc {}", path.display());
+    if parents.iter().flatten().any(|p| is_binary(p)) || result.is_some_and(is_binary) {
+        println!("Binary files differ");
+        return;
+    }
+
+    let result_text = String::from_utf8_lossy(result.unwrap_or_default()).into_owned();
+    let result_lines: Vec<&str> = result_text.lines().collect();
+    let parent_texts: Vec<String> = parents
+        .iter()
+        .map(|p| String::from_utf8_lossy(p.as_deref().unwrap_or_default()).into_owned())
+        .collect();
+
+    // For every parent: which result lines it already had, and which of its
+    // lines were removed just before each result line
+    let mut present = vec![vec![false; result_lines.len()]; parents.len()];
+    let mut removed: Vec<Vec<Vec<&str>>> = vec![vec![Vec::new(); result_lines.len() + 1]; parents.len()];
+    for (i, parent_text) in parent_texts.iter().enumerate() {
+        let mut line = 0;
+        for diff_line in diff_lines(parent_text, &result_text) {
+            match diff_line {
+                DiffLine::Equal(_) => {
+                    present[i][line] = true;
+                    line += 1;
+                }
+                DiffLine::Insert(_) => line += 1,
+                DiffLine::Delete(text) => removed[i][line].push(text),
+            }
+        }
+    }
+
+    for line in 0..=result_lines.len() {
+        for (i, parent_removed) in removed.iter().enumerate() {
+            for text in &parent_removed[line] {
+                let columns: String = (0..parents.len()).map(|p| if p == i { '-' } else { ' ' }).collect();
+                println!("{}", format!("{}{}", columns, text).red());
+            }
+        }
+        if let Some(text) = result_lines.get(line) {
+            let columns: String = present.iter().map(|p| if p[line] { ' ' } else { '+' }).collect();
+            let row = format!("{}{}", columns, text);
+            match columns.contains('+') {
+                true => println!("{}", row.green()),
+                false => println!("{}", row),
+            }
+        }
+    }
+}
+
+/// Every deleted and inserted line across a whole diff, used for `--color-moved`.
+struct MovedLines {
+    deleted: HashSet<String>,
+    inserted: HashSet<String>,
+}
+
+impl MovedLines {
+    fn collect(changes: &[FileChange]) -> Self {
+        let mut deleted = HashSet::new();
+        let mut inserted = HashSet::new();
+        for change in changes.iter().filter(|c| !c.is_binary()) {
+            let old = change.old_text();
+            let new = change.new_text();
+            for line in diff_lines(&old, &new) {
+                match line {
+                    DiffLine::Delete(text) => { deleted.insert(text.to_string()); }
+                    DiffLine::Insert(text) => { inserted.insert(text.to_string()); }
+                    DiffLine::Equal(_) => {}
+                }
+            }
+        }
+        MovedLines { deleted, inserted }
+    }
+
+    /// Flags the lines of one file's diff that belong to a moved block: a run
+    /// of deletions (or insertions) whose every line reappears on the other
+    /// side, and which is long enough to be meaningful.
+    fn mark<'a>(&self, lines: &[DiffLine<'a>]) -> Vec<bool> {
+        let candidate = |line: &DiffLine<'a>| -> Option<(u8, bool, &'a str)> {
+            match line {
+                DiffLine::Delete(text) => Some((0, self.inserted.contains(*text), *text)),
+                DiffLine::Insert(text) => Some((1, self.deleted.contains(*text), *text)),
+                DiffLine::Equal(_) => None,
+            }
+        };
+
+        let mut marks = vec![false; lines.len()];
+        let mut i = 0;
+        while i < lines.len() {
+            let Some((side, true, _)) = candidate(&lines[i]) else {
+                i += 1;
+                continue;
+            };
+            // Extend the block while lines stay on the same side and keep matching
+            let mut end = i;
+            let mut alnum = 0;
+            while let Some((s, true, text)) = lines.get(end).and_then(candidate) {
+                if s != side {
+                    break;
+                }
+                alnum += text.chars().filter(|c| c.is_alphanumeric()).count();
+                end += 1;
+            }
+            if alnum >= MOVED_BLOCK_MIN_ALNUM {
+                marks[i..end].fill(true);
+            }
+            i = end;
+        }
+        marks
+    }
+}
+
+/// Picks the word regex for a file: the command line wins, then
+/// `diff.<ext>.wordRegex` for the file's extension, then `diff.wordRegex`.
+fn word_regex(
+    config: &HashMap<String, String>,
+    path: &Path,
+    options: &DiffOptions,
+) -> io::Result<Regex> {
+    // Config keys are case-insensitive, so `diff.wordregex` counts too
+    let lookup = |name: &str| config.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value);
+    let by_extension = path
+        .extension()
+        .and_then(|ext| lookup(&format!("diff.{}.wordRegex", ext.to_string_lossy())));
+
+    let pattern = options
+        .word_regex
+        .as_ref()
+        .or(by_extension)
+        .or_else(|| lookup("diff.wordRegex"))
+        .map_or(DEFAULT_WORD_REGEX, String::as_str);
+
+    Regex::new(pattern).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("invalid word regex '{}': {}", pattern, e),
+        )
+    })
+}
+
+/// Splits text into word tokens (regex matches) and separator tokens.
+/// Newlines always become tokens of their own.
+fn tokenize<'a>(text: &'a str, regex: &Regex) -> Vec<(bool, &'a str)> {
+    fn push_separators<'a>(gap: &'a str, tokens: &mut Vec<(bool, &'a str)>) {
+        for piece in gap.split_inclusive('\n') {
+            match piece.strip_suffix('\n') {
+                Some(rest) => {
+                    if !rest.is_empty() {
+                        tokens.push((false, rest));
                     }
+                    tokens.push((false, "\n"));
+                }
+                None => tokens.push((false, piece)),
+            }
+        }
+    }
+
+    let mut tokens = Vec::new();
+    let mut last = 0;
+    for m in regex.find_iter(text) {
+        if m.is_empty() {
+            continue;
+        }
+        push_separators(&text[last..m.start()], &mut tokens);
+        tokens.push((true, m.as_str()));
+        last = m.end();
+    }
+    push_separators(&text[last..], &mut tokens);
+    tokens
+}
+
+/// Diffs two token streams. Tokens are compared one by one where possible;
+/// with more distinct tokens than can be encoded, whole lines are compared
+/// instead, and failing that everything old is replaced by everything new.
+fn diff_tokens<'a>(old: &[(bool, &'a str)], new: &[(bool, &'a str)]) -> Vec<(DiffLine<'a>, bool)> {
+    let (old_tokens, new_tokens): (Vec<_>, Vec<_>) = (old.chunks(1).collect(), new.chunks(1).collect());
+    if let Some(result) = diff_units(&old_tokens, &new_tokens) {
+        return result;
+    }
+    if let Some(result) = diff_units(&token_lines(old), &token_lines(new)) {
+        return result;
+    }
+    let deleted = old.iter().map(|&(is_word, token)| (DiffLine::Delete(token), is_word));
+    deleted.chain(new.iter().map(|&(is_word, token)| (DiffLine::Insert(token), is_word))).collect()
+}
+
+/// Groups a token stream into lines, each ending with the token that holds
+/// its newline.
+fn token_lines<'s, 'a>(stream: &'s [(bool, &'a str)]) -> Vec<&'s [(bool, &'a str)]> {
+    stream.split_inclusive(|(_, token)| token.contains('\n')).collect()
+}
+
+/// Diffs two sequences of token runs, expanding the runs back into tokens.
+/// Returns `None` if there are too many distinct runs to encode.
+fn diff_units<'s, 'a>(old: &[&'s [(bool, &'a str)]], new: &[&'s [(bool, &'a str)]]) -> Option<Vec<(DiffLine<'a>, bool)>> {
+    let edits = diff_sequences(old, new)?;
+    Some(
+        edits
+            .into_iter()
+            .flat_map(|(edit, unit)| unit.iter().map(move |&(is_word, token)| (edit.wrap(token), is_word)))
+            .collect(),
+    )
+}
+
+/// Prints a word-level diff for one file.
+fn print_word_diff(out: &mut dyn Write, change: &FileChange, mode: WordDiffMode, regex: &Regex) -> io::Result<()> {
+    print_header(out, change)?;
+
+    let old = change.old_text();
+    let new = change.new_text();
+    let ops = diff_tokens(&tokenize(&old, regex), &tokenize(&new, regex));
+
+    if mode == WordDiffMode::Porcelain {
+        return print_word_porcelain(out, &ops);
+    }
+
+    // Consecutive removed or added tokens are collected into one marked run
+    let mut marked = String::new();
+    let mut run: Option<(bool, String)> = None;
+    let flush = |run: &mut Option<(bool, String)>, marked: &mut String| {
+        if let Some((added, text)) = run.take() {
+            marked.push_str(&mark_words(&text, added, mode));
+        }
+    };
+
+    for (op, is_word) in ops {
+        match op {
+            DiffLine::Equal(token) => {
+                flush(&mut run, &mut marked);
+                marked.push_str(token);
+            }
+            // Removed whitespace carries no information on its own
+            DiffLine::Delete(_) if !is_word => {}
+            DiffLine::Insert(token) if !is_word && !matches!(run, Some((true, _))) => {
+                flush(&mut run, &mut marked);
+                marked.push_str(token);
+            }
+            DiffLine::Delete(token) | DiffLine::Insert(token) => {
+                let added = matches!(op, DiffLine::Insert(_));
+                if run.as_ref().is_some_and(|(a, _)| *a != added) {
+                    flush(&mut run, &mut marked);
                 }
+                run.get_or_insert_with(|| (added, String::new())).1.push_str(token);
             }
         }
     }
+    flush(&mut run, &mut marked);
 
+    write!(out, "{}", marked)?;
+    if !marked.ends_with('\n') {
+        writeln!(out)?;
+    }
     Ok(())
 }
+
+/// Wraps a run of changed tokens, marking each line of it separately.
+fn mark_words(text: &str, added: bool, mode: WordDiffMode) -> String {
+    let pieces: Vec<String> = text
+        .split('\n')
+        .map(|piece| {
+            let piece = piece.trim_end();
+            if piece.is_empty() {
+                return String::new();
+            }
+            match (mode, added) {
+                (WordDiffMode::Color, false) => piece.red().to_string(),
+                (WordDiffMode::Color, true) => piece.green().to_string(),
+                (_, false) => format!("[-{}-]", piece),
+                (_, true) => format!("{{+{}+}}", piece),
+            }
+        })
+        .collect();
+    pieces.join("\n")
+}
+
+/// `--word-diff=porcelain`: each run on its own line prefixed with ' ', '-'
+/// or '+', and '~' marking the end of a line in the new file.
+fn print_word_porcelain(out: &mut dyn Write, ops: &[(DiffLine, bool)]) -> io::Result<()> {
+    let mut current: Option<(char, String)> = None;
+    let flush = |current: &mut Option<(char, String)>, out: &mut dyn Write| match current.take() {
+        Some((prefix, text)) => writeln!(out, "{}{}", prefix, text),
+        None => Ok(()),
+    };
+
+    for (op, _) in ops {
+        let (prefix, token) = match op {
+            DiffLine::Equal(token) => (' ', *token),
+            DiffLine::Delete(token) => ('-', *token),
+            DiffLine::Insert(token) => ('+', *token),
+        };
+        if token == "\n" {
+            if prefix != '-' {
+                flush(&mut current, out)?;
+                writeln!(out, "~")?;
+            }
+            continue;
+        }
+        if current.as_ref().is_some_and(|(p, _)| *p != prefix) {
+            flush(&mut current, out)?;
+        }
+        current.get_or_insert_with(|| (prefix, String::new())).1.push_str(token);
+    }
+    flush(&mut current, out)
+}
+
+/// The alphabet Git uses for base85-encoded binary patches.
+const BASE85: &[u8; 85] =
+    b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz!#$%&()*+-;<=>?@^_`{|}~";
+
+/// Prints a `GIT binary patch` with a forward (new) and reverse (old) literal.
+fn print_binary_patch(out: &mut dyn Write, change: &FileChange) -> io::Result<()> {
+    writeln!(out, "GIT binary patch")?;
+    print_binary_literal(out, change.new.as_deref().unwrap_or_default())?;
+    print_binary_literal(out, change.old.as_deref().unwrap_or_default())
+}
+
+fn print_binary_literal(out: &mut dyn Write, data: &[u8]) -> io::Result<()> {
+    // Git deflates binary patches at zlib's default level; writing into an
+    // in-memory buffer cannot fail.
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let deflated = encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .unwrap_or_default();
+    writeln!(out, "literal {}", data.len())?;
+    for chunk in deflated.chunks(52) {
+        // Each line starts with its decoded length: A-Z for 1-26, a-z for 27-52.
+        let len = chunk.len() as u8;
+        let mut line = String::new();
+        line.push(if len <= 26 { (b'A' + len - 1) as char } else { (b'a' + len - 27) as char });
+
+        for group in chunk.chunks(4) {
+            let mut word = [0u8; 4];
+            word[..group.len()].copy_from_slice(group);
+            let mut value = u32::from_be_bytes(word);
+            let mut encoded = [0u8; 5];
+            for slot in encoded.iter_mut().rev() {
+                *slot = BASE85[(value % 85) as usize];
+                value /= 85;
+            }
+            line.extend(encoded.iter().map(|&b| b as char));
+        }
+        writeln!(out, "{}", line)?;
+    }
+    writeln!(out)
+}
@@ -0,0 +1,132 @@
+use super::config::read_config_file;
+use super::diff::{collect_changes, DiffOptions};
+use super::utils::find_repo_root;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+pub fn run(path: Option<&str>, tool: Option<&str>, cached: bool) -> io::Result<()> {
+    let repo_root = find_repo_root()?;
+    let work_dir = repo_root.parent().unwrap();
+    let config = read_config_file(&repo_root)?;
+
+    // diff.tool falls back to merge.tool, as most merge tools can also diff
+    let tool_name = tool
+        .map(str::to_string)
+        .or_else(|| config.get("diff.tool").cloned())
+        .or_else(|| config.get("merge.tool").cloned())
+        .ok_or_else(|| io::Error::other("No diff tool configured. Set one with: rit config diff.tool <tool>"))?;
+    let command = tool_command(&config, "difftool", &tool_name)?;
+
+    let options = DiffOptions { cached, ..Default::default() };
+    let changes = collect_changes(&repo_root, path, &options)?;
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let temp_dir = TempDir::new("difftool")?;
+    for change in &changes {
+        let old_path = change.old_path.as_ref().unwrap_or(&change.path);
+        let local = temp_dir.materialize(old_path, "LOCAL", change.old.as_deref().unwrap_or_default())?;
+
+        // Diffing against the working tree lets the tool edit the real file
+        let working_file = work_dir.join(&change.path);
+        let remote = if !cached && working_file.is_file() {
+            working_file
+        } else {
+            temp_dir.materialize(&change.path, "REMOTE", change.new.as_deref().unwrap_or_default())?
+        };
+
+        println!("Viewing: '{}'", change.path.display());
+        let status = run_tool(
+            &command,
+            &[("LOCAL", &local), ("REMOTE", &remote), ("MERGED", &remote), ("BASE", &local)],
+        )?;
+        if !status {
+            return Err(io::Error::other(format!(
+                "external diff tool '{}' exited with an error",
+                tool_name
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Built-in commands for well-known tools. Placeholders are passed to the
+/// shell as environment variables, so they are written `$LOCAL`, `$REMOTE` etc.
+fn builtin_command(section: &str, tool: &str) -> Option<&'static str> {
+    let command = match (section, tool) {
+        ("difftool", "meld") => r#"meld "$LOCAL" "$REMOTE""#,
+        ("difftool", "kdiff3") => r#"kdiff3 --L1 "$MERGED (A)" --L2 "$MERGED (B)" "$LOCAL" "$REMOTE""#,
+        ("difftool", "vimdiff") => r#"vimdiff -R -f -d "$LOCAL" "$REMOTE""#,
+        ("mergetool", "meld") => r#"meld "$LOCAL" "$BASE" "$REMOTE" --output "$MERGED""#,
+        ("mergetool", "kdiff3") => r#"kdiff3 --auto "$BASE" "$LOCAL" "$REMOTE" -o "$MERGED""#,
+        ("mergetool", "vimdiff") => r#"vimdiff -f -d -c "4wincmd w | wincmd J" "$LOCAL" "$BASE" "$REMOTE" "$MERGED""#,
+        _ => return None,
+    };
+    Some(command)
+}
+
+/// Resolves the command line for a tool: `<section>.<tool>.cmd` from the
+/// config wins over the built-in definitions.
+pub(crate) fn tool_command(config: &HashMap<String, String>, section: &str, tool: &str) -> io::Result<String> {
+    config
+        .get(&format!("{}.{}.cmd", section, tool))
+        .cloned()
+        .or_else(|| builtin_command(section, tool).map(str::to_string))
+        .ok_or_else(|| {
+            io::Error::other(format!(
+                "Unknown tool '{}'. Set a command with: rit config {}.{}.cmd '<command>'",
+                tool, section, tool
+            ))
+        })
+}
+
+/// Runs a tool command through the shell with the placeholder files exported
+/// as environment variables. Returns whether the tool exited successfully.
+pub(crate) fn run_tool(command: &str, files: &[(&str, &Path)]) -> io::Result<bool> {
+    let mut shell = Command::new("sh");
+    shell.arg("-c").arg(command);
+    for (name, path) in files {
+        shell.env(name, path);
+    }
+    let status = shell
+        .status()
+        .map_err(|e| io::Error::other(format!("failed to run '{}': {}", command, e)))?;
+    Ok(status.success())
+}
+
+/// A scratch directory for the versions of files handed to external tools,
+/// removed again when dropped.
+pub(crate) struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(purpose: &str) -> io::Result<Self> {
+        let path = std::env::temp_dir().join(format!("rit-{}-{}", purpose, std::process::id()));
+        fs::create_dir_all(&path)?;
+        Ok(TempDir { path })
+    }
+
+    /// Writes one version of a file as e.g. `main_LOCAL.rs`, keeping the
+    /// extension so that tools can still pick a syntax highlighter.
+    pub fn materialize(&self, path: &Path, label: &str, content: &[u8]) -> io::Result<PathBuf> {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let file_name = match path.extension() {
+            Some(ext) => format!("{}_{}.{}", stem, label, ext.to_string_lossy()),
+            None => format!("{}_{}", stem, label),
+        };
+        let temp_path = self.path.join(file_name);
+        fs::write(&temp_path, content)?;
+        Ok(temp_path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
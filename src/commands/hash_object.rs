@@ -0,0 +1,29 @@
+use super::utils::{blob_object, find_repo_root, write_object};
+use std::fs;
+use std::io::{self, Read};
+
+/// Prints the blob hash of each file (or of stdin), optionally writing the
+/// blobs into the object database.
+pub fn run(files: &[String], stdin: bool, write: bool) -> io::Result<()> {
+    // Hashing alone works outside a repository, like in Git
+    let repo_path = if write { Some(find_repo_root()?) } else { None };
+
+    let mut contents = Vec::new();
+    if stdin {
+        let mut data = Vec::new();
+        io::stdin().read_to_end(&mut data)?;
+        contents.push(data);
+    }
+    for file in files {
+        contents.push(fs::read(file).map_err(|e| io::Error::other(format!("could not read '{}': {}", file, e)))?);
+    }
+
+    for data in contents {
+        let (hash, object) = blob_object(&data);
+        if let Some(repo_path) = &repo_path {
+            write_object(repo_path, &hash, &object)?;
+        }
+        println!("{}", hash);
+    }
+    Ok(())
+}
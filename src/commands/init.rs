@@ -33,9 +33,8 @@ pub fn run() -> std::io::Result<()> {
     // Create config file
     let config_path = rit_path.join("config");
     let mut config_file = File::create(config_path)?;
-    config_file.write_all(b"[user]\n")?;
-    config_file.write_all(b"  name  = Default Name\n")?;
-    config_file.write_all(b"  email = Default Email\n")?;
+    config_file.write_all(b"[core]\n")?;
+    config_file.write_all(b"  fileMode = true\n")?;
 
 
     println!("Rit repository successfully initialized");
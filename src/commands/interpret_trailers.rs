@@ -0,0 +1,311 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+
+/// A `Key: value` line at the end of a commit message, such as
+/// `Signed-off-by: Ann Dev <ann@example.com>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Trailer {
+    pub key: String,
+    pub value: String,
+}
+
+impl Trailer {
+    /// Parses a `--trailer` argument, `key=value` or `key: value`.
+    pub fn parse_arg(arg: &str) -> io::Result<Trailer> {
+        let (key, value) = arg
+            .split_once(['=', ':'])
+            .filter(|(key, _)| is_token(key.trim()))
+            .ok_or_else(|| io::Error::other(format!("invalid trailer '{}': expected key=value", arg)))?;
+        Ok(Trailer { key: key.trim().to_string(), value: value.trim().to_string() })
+    }
+
+    /// Parses a trailer line of a message. Keys are made of letters,
+    /// digits and dashes, so `Note: see below` counts but `See: the docs` in
+    /// the middle of prose is only a trailer at the end of a message.
+    fn parse_line(line: &str) -> Option<Trailer> {
+        let (key, value) = line.split_once(':')?;
+        is_token(key).then(|| Trailer { key: key.to_string(), value: value.trim().to_string() })
+    }
+
+    /// Keys compare without regard to case, as in Git.
+    fn same_key(&self, other: &Trailer) -> bool {
+        self.key.eq_ignore_ascii_case(&other.key)
+    }
+}
+
+impl fmt::Display for Trailer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.key, self.value)
+    }
+}
+
+fn is_token(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// What adding a trailer does when the message already has one with the
+/// same key (`--if-exists`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+#[value(rename_all = "camelCase")]
+pub enum IfExists {
+    /// Add it unless the last trailer is exactly the same
+    #[default]
+    AddIfDifferentNeighbor,
+    /// Add it unless an identical trailer exists anywhere
+    AddIfDifferent,
+    /// Always add it
+    Add,
+    /// Remove the existing ones with that key, then add it
+    Replace,
+    /// Keep the existing ones and don't add it
+    DoNothing,
+}
+
+/// Trailers Git itself writes. A last paragraph mixing prose and trailers
+/// still counts as a trailer block if it has one of these.
+const KNOWN_TRAILER_PREFIXES: &[&str] = &["Signed-off-by: ", "(cherry picked from commit "];
+
+/// One line of a trailer block as written (with any continuation lines),
+/// and the trailer it holds if it is not prose.
+type BlockLine = (String, Option<Trailer>);
+
+/// Splits a message into the text before its trailer block and the lines
+/// of that block. The trailer block is the last paragraph, if every line
+/// in it is a trailer or indented to continue the one above, or if at
+/// least a quarter of it is trailers and one is written by Git itself,
+/// like `Signed-off-by`. The first paragraph is the subject and never
+/// holds trailers.
+fn split_trailers(message: &str) -> (&str, Vec<BlockLine>) {
+    let message = message.trim_end();
+    let Some(start) = message.rfind("\n\n").map(|i| i + 2) else {
+        return (message, Vec::new());
+    };
+
+    let mut block: Vec<BlockLine> = Vec::new();
+    for line in message[start..].lines() {
+        if line.starts_with([' ', '\t'])
+            && let Some((raw, Some(trailer))) = block.last_mut()
+        {
+            // Continuation lines fold into the value
+            raw.push('\n');
+            raw.push_str(line);
+            trailer.value = format!("{} {}", trailer.value, line.trim());
+            continue;
+        }
+        block.push((line.to_string(), Trailer::parse_line(line)));
+    }
+
+    let is_known = |raw: &str| KNOWN_TRAILER_PREFIXES.iter().any(|prefix| raw.starts_with(prefix));
+    let trailers = block.iter().filter(|(raw, trailer)| trailer.is_some() || is_known(raw)).count();
+    let known = block.iter().any(|(raw, _)| is_known(raw));
+    if trailers == block.len() || (known && trailers * 4 >= block.len()) {
+        (message[..start].trim_end(), block)
+    } else {
+        (message, Vec::new())
+    }
+}
+
+/// The trailers at the end of a commit message.
+pub(crate) fn parse_trailers(message: &str) -> Vec<Trailer> {
+    split_trailers(message).1.into_iter().filter_map(|(_, trailer)| trailer).collect()
+}
+
+/// Adds trailers to the end of a message, starting a trailer block if
+/// there is none. Lines already in the block are kept as they are.
+pub(crate) fn add_trailers(message: &str, new: &[Trailer], if_exists: IfExists) -> String {
+    let (text, mut block) = split_trailers(message);
+    for trailer in new {
+        let mut existing = block.iter().filter_map(|(_, trailer)| trailer.as_ref());
+        let add = match if_exists {
+            IfExists::AddIfDifferentNeighbor => !existing.next_back().is_some_and(|last| last.same_key(trailer) && last.value == trailer.value),
+            IfExists::AddIfDifferent => !existing.any(|t| t.same_key(trailer) && t.value == trailer.value),
+            IfExists::Add => true,
+            IfExists::Replace => {
+                block.retain(|(_, t)| !t.as_ref().is_some_and(|t| t.same_key(trailer)));
+                true
+            }
+            IfExists::DoNothing => !existing.any(|t| t.same_key(trailer)),
+        };
+        if add {
+            block.push((trailer.to_string(), Some(trailer.clone())));
+        }
+    }
+
+    let block: Vec<&str> = block.iter().map(|(raw, _)| raw.as_str()).collect();
+    match (text.is_empty(), block.is_empty()) {
+        (_, true) => text.to_string(),
+        (true, false) => block.join("\n"),
+        (false, false) => format!("{}\n\n{}", text, block.join("\n")),
+    }
+}
+
+/// Options for `rit interpret-trailers`.
+#[derive(Debug, Default)]
+pub struct InterpretTrailersOptions {
+    /// Trailers to add, as `key=value`
+    pub trailers: Vec<String>,
+    pub if_exists: IfExists,
+    /// Print only the trailers, adding nothing
+    pub parse: bool,
+    /// Print only the trailers, after adding any new ones
+    pub only_trailers: bool,
+    /// Rewrite the files instead of printing
+    pub in_place: bool,
+}
+
+/// Adds trailers to the messages in `files` (standard input when there
+/// are none) and prints the results, or with `--parse` prints just the
+/// trailers they already have.
+pub fn run(files: &[String], options: &InterpretTrailersOptions) -> io::Result<()> {
+    let new: Vec<Trailer> = match options.parse {
+        true => Vec::new(),
+        false => options.trailers.iter().map(|arg| Trailer::parse_arg(arg)).collect::<io::Result<_>>()?,
+    };
+    if files.is_empty() && options.in_place {
+        return Err(io::Error::other("no input file given for --in-place"));
+    }
+
+    let mut inputs = Vec::new();
+    if files.is_empty() {
+        let mut message = String::new();
+        io::stdin().read_to_string(&mut message)?;
+        inputs.push((None, message));
+    }
+    for file in files {
+        let message = fs::read_to_string(file).map_err(|e| io::Error::other(format!("could not read '{}': {}", file, e)))?;
+        inputs.push((Some(file), message));
+    }
+
+    for (file, message) in inputs {
+        let output = match options.parse || options.only_trailers {
+            true => parse_trailers(&add_trailers(&message, &new, options.if_exists))
+                .iter()
+                .map(|trailer| format!("{}\n", trailer))
+                .collect(),
+            false => format!("{}\n", add_trailers(&message, &new, options.if_exists)),
+        };
+        match (file, options.in_place) {
+            (Some(file), true) => fs::write(file, output)?,
+            _ => print!("{}", output),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trailer(key: &str, value: &str) -> Trailer {
+        Trailer { key: key.to_string(), value: value.to_string() }
+    }
+
+    const SIGNED: &str = "Subject\n\nBody text.\n\nSigned-off-by: Ann <ann@example.com>";
+
+    #[test]
+    fn parses_trailer_arguments() {
+        assert_eq!(Trailer::parse_arg("Fixes=123").unwrap(), trailer("Fixes", "123"));
+        assert_eq!(Trailer::parse_arg(" Acked-by : Bob ").unwrap(), trailer("Acked-by", "Bob"));
+        assert!(Trailer::parse_arg("no separator").is_err());
+        assert!(Trailer::parse_arg("two words: value").is_err());
+        assert!(Trailer::parse_arg("=value").is_err());
+    }
+
+    #[test]
+    fn subject_is_never_a_trailer() {
+        assert!(parse_trailers("Fixes: 12").is_empty());
+        assert_eq!(add_trailers("Fixes: 12", &[trailer("Fixes", "12")], IfExists::Add), "Fixes: 12\n\nFixes: 12");
+    }
+
+    #[test]
+    fn last_paragraph_of_only_trailers_is_a_block() {
+        let message = "Subject\n\nFixes: 12\nSee-also: 34";
+        assert_eq!(parse_trailers(message), vec![trailer("Fixes", "12"), trailer("See-also", "34")]);
+        assert!(parse_trailers("Subject\n\nSee the docs: they explain it\nFixes: 12").is_empty());
+    }
+
+    #[test]
+    fn known_prefixes_accept_mixed_paragraphs() {
+        let message = "Subject\n\nReviewed on the list\nSigned-off-by: Ann <ann@example.com>";
+        assert_eq!(parse_trailers(message), vec![trailer("Signed-off-by", "Ann <ann@example.com>")]);
+
+        // The cherry-pick note is not `Key: value` but still counts towards the block
+        let message = "Subject\n\nprose\n(cherry picked from commit 0123abc)\nFixes: 12\nprose";
+        assert_eq!(parse_trailers(message), vec![trailer("Fixes", "12")]);
+
+        // Without a trailer Git writes, prose means the paragraph is prose
+        assert!(parse_trailers("Subject\n\nprose\nFixes: 12").is_empty());
+    }
+
+    #[test]
+    fn a_quarter_of_the_block_must_be_trailers() {
+        let at_threshold = "Subject\n\none\ntwo\nthree\nSigned-off-by: Ann <ann@example.com>";
+        assert_eq!(parse_trailers(at_threshold).len(), 1);
+
+        let below = "Subject\n\none\ntwo\nthree\nfour\nSigned-off-by: Ann <ann@example.com>";
+        assert!(parse_trailers(below).is_empty());
+        assert_eq!(
+            add_trailers(below, &[trailer("Fixes", "12")], IfExists::Add),
+            format!("{}\n\nFixes: 12", below)
+        );
+    }
+
+    #[test]
+    fn existing_lines_are_kept_verbatim() {
+        let message = "Subject\n\nKey:   spaced value\nSee-also: a\n  continued here";
+        assert_eq!(parse_trailers(message), vec![trailer("Key", "spaced value"), trailer("See-also", "a continued here")]);
+        assert_eq!(
+            add_trailers(message, &[trailer("Fixes", "12")], IfExists::Add),
+            format!("{}\nFixes: 12", message)
+        );
+    }
+
+    #[test]
+    fn starts_a_block_when_there_is_none() {
+        assert_eq!(
+            add_trailers("Subject\n\nBody text.\n", &[trailer("Fixes", "12")], IfExists::Add),
+            "Subject\n\nBody text.\n\nFixes: 12"
+        );
+        assert_eq!(add_trailers("", &[trailer("Fixes", "12")], IfExists::Add), "Fixes: 12");
+    }
+
+    #[test]
+    fn if_exists_add_if_different_neighbor() {
+        let same = trailer("signed-off-by", "Ann <ann@example.com>");
+        assert_eq!(add_trailers(SIGNED, &[same], IfExists::AddIfDifferentNeighbor), SIGNED);
+
+        let message = format!("{}\nFixes: 12", SIGNED);
+        let added = add_trailers(&message, &[trailer("Signed-off-by", "Ann <ann@example.com>")], IfExists::AddIfDifferentNeighbor);
+        assert_eq!(added, format!("{}\nSigned-off-by: Ann <ann@example.com>", message));
+    }
+
+    #[test]
+    fn if_exists_add_if_different() {
+        let message = format!("{}\nFixes: 12", SIGNED);
+        let same = trailer("Signed-off-by", "Ann <ann@example.com>");
+        assert_eq!(add_trailers(&message, &[same], IfExists::AddIfDifferent), message);
+
+        let other = trailer("Signed-off-by", "Bob <bob@example.com>");
+        assert_eq!(add_trailers(&message, &[other], IfExists::AddIfDifferent), format!("{}\nSigned-off-by: Bob <bob@example.com>", message));
+    }
+
+    #[test]
+    fn if_exists_add() {
+        let same = trailer("Signed-off-by", "Ann <ann@example.com>");
+        assert_eq!(add_trailers(SIGNED, &[same], IfExists::Add), format!("{}\nSigned-off-by: Ann <ann@example.com>", SIGNED));
+    }
+
+    #[test]
+    fn if_exists_replace() {
+        let message = "Subject\n\nSee-also: a\n  continued\nFixes: 12\nsee-also: b";
+        assert_eq!(add_trailers(message, &[trailer("See-also", "c")], IfExists::Replace), "Subject\n\nFixes: 12\nSee-also: c");
+    }
+
+    #[test]
+    fn if_exists_do_nothing() {
+        let other = trailer("SIGNED-OFF-BY", "Bob <bob@example.com>");
+        assert_eq!(add_trailers(SIGNED, &[other], IfExists::DoNothing), SIGNED);
+        assert_eq!(add_trailers(SIGNED, &[trailer("Fixes", "12")], IfExists::DoNothing), format!("{}\nFixes: 12", SIGNED));
+    }
+}
@@ -1,49 +1,715 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
 use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
 
-use super::utils::{find_repo_root, read_head_commit, read_object};
+use colored::Colorize;
+use regex::Regex;
 
-pub fn run() -> io::Result<()> {
+use super::commit::{read_commit, Commit, Signature};
+use super::diff::{changes_between, detect_renames, print_changes, write_changes, ChangeKind, DiffOptions, FileChange, FileMap, DEFAULT_SIMILARITY};
+use super::date::civil_from_days;
+use super::merge::ancestors;
+use super::utils::{find_repo_root, get_current_branch, get_tree_hash, list_refs, read_head_commit, read_tree_entries};
+use super::revision::resolve_commit;
+use super::tag::tagged_commit;
+
+/// The `--pretty` layouts for a commit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Pretty {
+    Oneline,
+    Short,
+    #[default]
+    Medium,
+    Full,
+    Fuller,
+    /// A user-supplied template with `%` placeholders
+    Format(String),
+}
+
+impl Pretty {
+    /// Formats that print one line per commit, without blank separators.
+    pub(crate) fn is_single_line(&self) -> bool {
+        matches!(self, Pretty::Oneline | Pretty::Format(_))
+    }
+}
+
+/// Parses `--pretty` values: a layout name, `format:<fmt>`/`tformat:<fmt>`,
+/// or a bare template containing placeholders.
+pub fn parse_pretty(value: &str) -> Result<Pretty, String> {
+    if let Some(template) = value.strip_prefix("format:").or_else(|| value.strip_prefix("tformat:")) {
+        return Ok(Pretty::Format(template.to_string()));
+    }
+    match value {
+        "oneline" => Ok(Pretty::Oneline),
+        "short" => Ok(Pretty::Short),
+        "medium" => Ok(Pretty::Medium),
+        "full" => Ok(Pretty::Full),
+        "fuller" => Ok(Pretty::Fuller),
+        template if template.contains('%') => Ok(Pretty::Format(template.to_string())),
+        _ => Err(format!("invalid pretty format: {}", value)),
+    }
+}
+
+/// How `--date` shows commit times.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DateMode {
+    /// `Thu Nov 14 12:00:00 2023 +0530`
+    #[default]
+    Default,
+    /// `2023-11-14 12:00:00 +0530`
+    Iso,
+    /// `3 hours ago`
+    Relative,
+    /// `2023-11-14`
+    Short,
+    /// Seconds since the Unix epoch
+    Unix,
+}
+
+/// Everything that decides how a commit header is printed.
+#[derive(Debug, Clone, Default)]
+pub struct CommitFormat {
+    pub pretty: Pretty,
+    pub date: DateMode,
+    /// Show abbreviated commit hashes
+    pub abbrev_commit: bool,
+}
+
+/// Options controlling what `rit log` shows for each commit.
+#[derive(Debug, Default)]
+pub struct LogOptions {
+    /// List the files each commit changed, with their A/M/D/R/C status
+    pub name_status: bool,
+    pub find_renames: Option<u8>,
+    pub find_copies: Option<u8>,
+    /// Only show commits touching this file, following it across renames
+    pub follow: Option<String>,
+    /// How each commit is laid out
+    pub format: CommitFormat,
+    /// Show each commit's patch against its first parent
+    pub patch: bool,
+    /// Show a diffstat of each commit's changes
+    pub stat: bool,
+    /// Stop after this many commits
+    pub max_count: Option<usize>,
+    /// Draw the commit graph next to the log
+    pub graph: bool,
+    /// Start from every branch and tag instead of only HEAD
+    pub all: bool,
+    /// Only follow the first parent of merge commits
+    pub first_parent: bool,
+    /// Revisions and ranges to start from (`main`, `A..B`, `A...B`, `^X`)
+    pub revisions: Vec<String>,
+    /// Only show commits that changed one of these paths
+    pub paths: Vec<String>,
+    /// Only show commits whose `Name <email>` matches this regex
+    pub author: Option<String>,
+    /// Only show commits whose message matches this regex
+    pub grep: Option<String>,
+    /// Only show commits authored at or after this timestamp
+    pub since: Option<i64>,
+    /// Only show commits authored at or before this timestamp
+    pub until: Option<i64>,
+}
+
+pub fn run(options: &LogOptions) -> io::Result<()> {
     let repo_path = find_repo_root()?;
-    let head_hash = read_head_commit(&repo_path)?;
-
-    let mut commit_hash = head_hash.clone();
-    while let Some(hash) = commit_hash {
-        let commit_data = read_object(&repo_path, &hash)?;
-
-        // Skip commit object header: "commit <size>\0"
-        let content = String::from_utf8_lossy(&commit_data);
-        
-        // Split header from commit message
-        let parts: Vec<&str> = content.splitn(2, "\n\n").collect();
-        let header_lines = parts.first().unwrap_or(&"");
-        let message = parts.get(1).unwrap_or(&"");
-
-        println!("commit {}", hash);
-
-        let mut parent: Option<String> = None;
-
-        for line in header_lines.lines() {
-            if let Some(tree_hash) = line.strip_prefix("tree ") {
-                println!("Tree: {}", tree_hash);
-            } else if let Some(parent_hash) = line.strip_prefix("parent ") {
-                parent = Some(parent_hash.to_string());
-            } else if line.starts_with("author ") {
+
+    let (mut starts, excluded) = parse_revisions(&repo_path, &options.revisions)?;
+    if options.all {
+        starts.extend(list_refs(&repo_path, "heads")?.into_iter().map(|(_, hash)| hash));
+        // Annotated tags point at tag objects; start from the commits they tag
+        starts.extend(list_refs(&repo_path, "tags")?.into_iter().filter_map(|(_, hash)| tagged_commit(&repo_path, &hash)));
+    }
+    if starts.is_empty() && options.revisions.is_empty() {
+        println!("No commits yet");
+        return Ok(());
+    }
+
+    let author = compile_filter(options.author.as_deref())?;
+    let grep = compile_filter(options.grep.as_deref())?;
+    let paths: Vec<PathBuf> = options.paths.iter().map(PathBuf::from).collect();
+
+    let commits = walk_commits(&repo_path, &starts, &excluded, options.first_parent)?;
+    let walked: HashSet<&str> = commits.iter().map(|(hash, _)| hash.as_str()).collect();
+    let decorations = decorations(&repo_path)?;
+    let mut graph = Graph::default();
+
+    // With --follow, the path we track changes whenever the file was renamed
+    let mut followed: Option<PathBuf> = options.follow.as_ref().map(PathBuf::from);
+    let find_renames = match followed {
+        Some(_) => Some(options.find_renames.unwrap_or(DEFAULT_SIMILARITY)),
+        None => options.find_renames,
+    };
+
+    let mut shown = 0;
+    for (hash, commit) in &commits {
+        if options.max_count.is_some_and(|max| shown >= max) {
+            break;
+        }
+        let parents = followed_parents(commit, options.first_parent);
+        // Parents outside the walked range get no lane in the graph
+        let graph_parents: Vec<String> = parents.iter().filter(|p| walked.contains(p.as_str())).cloned().collect();
+
+        let matches = author.as_ref().is_none_or(|re| re.is_match(&format!("{} <{}>", commit.author.name, commit.author.email)))
+            && grep.as_ref().is_none_or(|re| re.is_match(&commit.message))
+            && options.since.is_none_or(|since| commit.author.timestamp >= since)
+            && options.until.is_none_or(|until| commit.author.timestamp <= until);
+        if !matches || (!paths.is_empty() && !touches_paths(&repo_path, commit, &parents, &paths)?) {
+            graph.skip(hash, &graph_parents);
+            continue;
+        }
+
+        // Only compute the commit's changes when something needs them. Like
+        // Git, merges show no changes unless we only follow first parents.
+        let mut changes = Vec::new();
+        let needs_changes = options.name_status || options.stat || options.patch || followed.is_some();
+        if needs_changes && parents.len() <= 1 {
+            changes = commit_changes(&repo_path, &commit.tree, parents.first().map(String::as_str))?;
+            changes = detect_renames(changes, find_renames, options.find_copies);
+        }
+
+        if let Some(path) = &followed {
+            let Some(change) = changes.into_iter().find(|c| &c.path == path) else {
+                graph.skip(hash, &graph_parents);
+                continue;
+            };
+            // Keep following the file under its previous name
+            if let (ChangeKind::Renamed(_) | ChangeKind::Copied(_), Some(old_path)) = (change.kind, &change.old_path) {
+                followed = Some(old_path.clone());
+            }
+            changes = vec![change];
+        }
+        shown += 1;
+
+        if shown > 1 && !options.format.pretty.is_single_line() {
+            graph.println(options.graph, "");
+        }
+        let lines = format_commit(hash, commit, decorations.get(hash), &options.format);
+        if options.graph {
+            graph.print(hash, &graph_parents, &lines);
+        } else {
+            for line in &lines {
                 println!("{}", line);
             }
         }
 
-        // Print commit message with indentation
-        if !message.is_empty() {
-            println!("\n    {}", message.trim());
+        let diff_options = DiffOptions {
+            name_status: options.name_status,
+            stat: options.stat,
+            patch: options.patch,
+            ..Default::default()
+        };
+        if !changes.is_empty() && (options.name_status || options.stat || options.patch) {
+            if !options.format.pretty.is_single_line() {
+                graph.println(options.graph, "");
+            }
+            if options.graph {
+                // Keep the graph columns running alongside the diff output
+                let mut out = Vec::new();
+                write_changes(&mut out, &repo_path, &changes, &diff_options)?;
+                for line in String::from_utf8_lossy(&out).lines() {
+                    graph.println(true, line);
+                }
+            } else {
+                print_changes(&repo_path, &changes, &diff_options)?;
+            }
         }
+    }
+
+    Ok(())
+}
 
-        println!();
-        commit_hash = parent;
+/// The parents a traversal follows from this commit.
+fn followed_parents(commit: &Commit, first_parent: bool) -> Vec<String> {
+    match first_parent {
+        true => commit.parents.iter().take(1).cloned().collect(),
+        false => commit.parents.clone(),
     }
+}
 
-    if commit_hash.is_none() && head_hash.is_none() {
-        println!("No commits yet");
+/// Turns the revision arguments into the commits to start from and the set
+/// of commits to leave out. `A..B` means "B but not A", `A...B` means
+/// "either side but not both" and `^X` excludes X; an empty side is HEAD.
+fn parse_revisions(repo_path: &Path, revisions: &[String]) -> io::Result<(Vec<String>, HashSet<String>)> {
+    let resolve = |name: &str| match name {
+        "" => resolve_commit(repo_path, "HEAD"),
+        name => resolve_commit(repo_path, name),
+    };
+
+    let mut starts = Vec::new();
+    let mut excluded = HashSet::new();
+    for revision in revisions {
+        if let Some((left, right)) = revision.split_once("...") {
+            let (left, right) = (resolve(left)?, resolve(right)?);
+            let right_ancestors = ancestors(repo_path, &right)?;
+            excluded.extend(ancestors(repo_path, &left)?.into_iter().filter(|hash| right_ancestors.contains(hash)));
+            starts.extend([left, right]);
+        } else if let Some((left, right)) = revision.split_once("..") {
+            excluded.extend(ancestors(repo_path, &resolve(left)?)?);
+            starts.push(resolve(right)?);
+        } else if let Some(name) = revision.strip_prefix('^') {
+            excluded.extend(ancestors(repo_path, &resolve(name)?)?);
+        } else {
+            starts.push(resolve(revision)?);
+        }
     }
+    if revisions.is_empty() {
+        starts.extend(read_head_commit(repo_path)?);
+    }
+    Ok((starts, excluded))
+}
 
-    Ok(())
+fn compile_filter(pattern: Option<&str>) -> io::Result<Option<Regex>> {
+    pattern
+        .map(|pattern| Regex::new(pattern).map_err(|e| io::Error::other(format!("invalid regex '{}': {}", pattern, e))))
+        .transpose()
+}
+
+/// Whether a commit changed anything under the given paths. Like Git, a
+/// merge only counts if it differs from every parent there.
+fn touches_paths(repo_path: &Path, commit: &Commit, parents: &[String], paths: &[PathBuf]) -> io::Result<bool> {
+    let entries_under = |tree: &str| -> io::Result<FileMap> {
+        let mut entries = read_tree_entries(repo_path, tree)?;
+        entries.retain(|path, _| paths.iter().any(|p| path.starts_with(p)));
+        Ok(entries)
+    };
+
+    let own = entries_under(&commit.tree)?;
+    if parents.is_empty() {
+        return Ok(!own.is_empty());
+    }
+    for parent in parents {
+        if entries_under(&get_tree_hash(repo_path, parent)?)? == own {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Collects every commit reachable from `starts` but not `excluded`, and
+/// orders them so that a commit is always listed before its parents, newest
+/// commit date first among those that are ready to be shown.
+pub(crate) fn walk_commits(
+    repo_path: &Path,
+    starts: &[String],
+    excluded: &HashSet<String>,
+    first_parent: bool,
+) -> io::Result<Vec<(String, Commit)>> {
+    let mut commits: HashMap<String, Commit> = HashMap::new();
+    let mut pending: Vec<String> = starts.to_vec();
+    while let Some(hash) = pending.pop() {
+        if commits.contains_key(&hash) || excluded.contains(&hash) {
+            continue;
+        }
+        let commit = read_commit(repo_path, &hash)?;
+        pending.extend(followed_parents(&commit, first_parent));
+        commits.insert(hash, commit);
+    }
+    let edges = |commit: &Commit| -> Vec<String> {
+        followed_parents(commit, first_parent).into_iter().filter(|p| commits.contains_key(p)).collect()
+    };
+
+    // Count the children of every commit; a commit becomes ready once all of
+    // its children have been emitted
+    let mut children: HashMap<String, usize> = HashMap::new();
+    for commit in commits.values() {
+        for parent in edges(commit) {
+            *children.entry(parent).or_default() += 1;
+        }
+    }
+
+    // The sequence number keeps the order stable among equal dates, preferring
+    // commits that became ready first
+    let mut seq = 0usize;
+    let mut ready = BinaryHeap::new();
+    let mut seen_starts = Vec::new();
+    for start in starts {
+        if commits.contains_key(start) && !children.contains_key(start) && !seen_starts.contains(start) {
+            ready.push((commits[start].committer.timestamp, Reverse(seq), start.clone()));
+            seq += 1;
+            seen_starts.push(start.clone());
+        }
+    }
+
+    let mut ordered = Vec::with_capacity(commits.len());
+    while let Some((_, _, hash)) = ready.pop() {
+        for parent in edges(&commits[&hash]) {
+            let remaining = children.get_mut(&parent).unwrap();
+            *remaining -= 1;
+            if *remaining == 0 {
+                ready.push((commits[&parent].committer.timestamp, Reverse(seq), parent));
+                seq += 1;
+            }
+        }
+        ordered.push(hash);
+    }
+
+    Ok(ordered
+        .into_iter()
+        .map(|hash| {
+            let commit = commits.remove(&hash).unwrap();
+            (hash, commit)
+        })
+        .collect())
+}
+
+/// Maps commit hashes to the names pointing at them: `HEAD -> main`, other
+/// branches and `tag: v1.0`.
+pub(crate) fn decorations(repo_path: &Path) -> io::Result<HashMap<String, Vec<String>>> {
+    let mut names: HashMap<String, Vec<String>> = HashMap::new();
+    let current = get_current_branch()?;
+    let head = read_head_commit(repo_path)?;
+
+    if let (None, Some(head)) = (&current, &head) {
+        names.entry(head.clone()).or_default().push("HEAD".to_string());
+    }
+    for (branch, hash) in list_refs(repo_path, "heads")? {
+        let label = match &current {
+            Some(current) if *current == branch => format!("HEAD -> {}", branch),
+            _ => branch,
+        };
+        // Keep `HEAD -> branch` in front of the other names
+        let entry = names.entry(hash).or_default();
+        match label.starts_with("HEAD") {
+            true => entry.insert(0, label),
+            false => entry.push(label),
+        }
+    }
+    for (tag, hash) in list_refs(repo_path, "tags")? {
+        if let Some(commit) = tagged_commit(repo_path, &hash) {
+            names.entry(commit).or_default().push(format!("tag: {}", tag));
+        }
+    }
+    Ok(names)
+}
+
+fn format_decoration(names: &[String]) -> String {
+    let colored: Vec<String> = names
+        .iter()
+        .map(|name| match name.strip_prefix("HEAD -> ") {
+            Some(branch) => format!("{} {}", "HEAD ->".cyan().bold(), branch.green().bold()),
+            None if name == "HEAD" => name.cyan().bold().to_string(),
+            None if name.starts_with("tag: ") => name.yellow().bold().to_string(),
+            None => name.green().bold().to_string(),
+        })
+        .collect();
+    format!(" {}{}{}", "(".yellow(), colored.join(&", ".yellow().to_string()), ")".yellow())
+}
+
+/// Lays out a commit's header and message as lines, without a trailing
+/// blank line.
+pub(crate) fn format_commit(hash: &str, commit: &Commit, decoration: Option<&Vec<String>>, format: &CommitFormat) -> Vec<String> {
+    if let Pretty::Format(template) = &format.pretty {
+        return expand_format(template, hash, commit, decoration.map(|names| names.join(", ")), format.date);
+    }
+    let shown_hash = if format.abbrev_commit { &hash[..7] } else { hash };
+    let decoration = decoration.map(|names| format_decoration(names)).unwrap_or_default();
+    let signature = |label: &str, signature: &Signature| format!("{}{} <{}>", label, signature.name, signature.email);
+
+    let mut lines = Vec::new();
+    match &format.pretty {
+        Pretty::Oneline => lines.push(format!("{}{} {}", shown_hash.yellow(), decoration, commit.summary())),
+        pretty => {
+            lines.push(format!("{}{}", format!("commit {}", shown_hash).yellow(), decoration));
+            if commit.parents.len() > 1 {
+                let short: Vec<&str> = commit.parents.iter().map(|p| &p[..7]).collect();
+                lines.push(format!("Merge: {}", short.join(" ")));
+            }
+            match pretty {
+                Pretty::Short => lines.push(signature("Author: ", &commit.author)),
+                Pretty::Medium => {
+                    lines.push(signature("Author: ", &commit.author));
+                    lines.push(format!("Date:   {}", format_date(&commit.author, format.date)));
+                }
+                Pretty::Full => {
+                    lines.push(signature("Author: ", &commit.author));
+                    lines.push(signature("Commit: ", &commit.committer));
+                }
+                _ => {
+                    lines.push(signature("Author:     ", &commit.author));
+                    lines.push(format!("AuthorDate: {}", format_date(&commit.author, format.date)));
+                    lines.push(signature("Commit:     ", &commit.committer));
+                    lines.push(format!("CommitDate: {}", format_date(&commit.committer, format.date)));
+                }
+            }
+            lines.push(String::new());
+            match pretty {
+                Pretty::Short => lines.push(format!("    {}", commit.summary())),
+                _ => lines.extend(commit.message.trim_end().lines().map(|line| format!("    {}", line))),
+            }
+        }
+    }
+    lines
+}
+
+/// Expands a `--pretty=format:` template into lines.
+fn expand_format(template: &str, hash: &str, commit: &Commit, names: Option<String>, date: DateMode) -> Vec<String> {
+    let mut out = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            out.push(c);
+            continue;
+        }
+        let Some(key) = chars.next() else {
+            out.push('%');
+            break;
+        };
+        match key {
+            '%' => out.push('%'),
+            'n' => out.push('\n'),
+            'H' => out.push_str(hash),
+            'h' => out.push_str(&hash[..7]),
+            'T' => out.push_str(&commit.tree),
+            't' => out.push_str(&commit.tree[..7.min(commit.tree.len())]),
+            'P' => out.push_str(&commit.parents.join(" ")),
+            'p' => out.push_str(&commit.parents.iter().map(|p| &p[..7]).collect::<Vec<_>>().join(" ")),
+            's' => out.push_str(commit.summary()),
+            'b' => out.push_str(&commit.body()),
+            'B' => out.push_str(commit.message.trim_end()),
+            '(' => {
+                let mut placeholder = String::new();
+                for c in chars.by_ref() {
+                    if c == ')' {
+                        break;
+                    }
+                    placeholder.push(c);
+                }
+                match expand_trailers(&placeholder, commit) {
+                    Some(expanded) => out.push_str(&expanded),
+                    None => out.push_str(&format!("%({})", placeholder)),
+                }
+            }
+            'd' => out.push_str(&names.as_ref().map(|n| format!(" ({})", n)).unwrap_or_default()),
+            'D' => out.push_str(names.as_deref().unwrap_or_default()),
+            'a' | 'c' => {
+                let signature = if key == 'a' { &commit.author } else { &commit.committer };
+                let expanded = match chars.peek() {
+                    Some('n') => signature.name.clone(),
+                    Some('e') => signature.email.clone(),
+                    Some('d') => format_date(signature, date),
+                    Some('r') => format_date(signature, DateMode::Relative),
+                    Some('t') => signature.timestamp.to_string(),
+                    Some('i') => format_date(signature, DateMode::Iso),
+                    _ => {
+                        out.push('%');
+                        out.push(key);
+                        continue;
+                    }
+                };
+                chars.next();
+                out.push_str(&expanded);
+            }
+            other => {
+                out.push('%');
+                out.push(other);
+            }
+        }
+    }
+    out.split('\n').map(str::to_string).collect()
+}
+
+/// Expands `%(trailers[:options])`: the commit's trailers, one per line.
+/// `key=<key>` keeps only that key (repeatable), `valueonly` drops the
+/// keys and `separator=<sep>` joins them with `sep` instead. Returns `None`
+/// for any other placeholder.
+fn expand_trailers(placeholder: &str, commit: &Commit) -> Option<String> {
+    let options = match placeholder.strip_prefix("trailers") {
+        Some("") => "",
+        Some(options) => options.strip_prefix(':')?,
+        None => return None,
+    };
+
+    let mut keys = Vec::new();
+    let mut value_only = false;
+    let mut separator = None;
+    for option in options.split(',').filter(|option| !option.is_empty()) {
+        match option.split_once('=') {
+            Some(("key", key)) => keys.push(key.trim_end_matches(':').to_string()),
+            Some(("separator", sep)) => separator = Some(sep.replace("%n", "\n")),
+            None if option == "valueonly" => value_only = true,
+            _ => return None,
+        }
+    }
+
+    let trailers: Vec<String> = commit
+        .trailers()
+        .into_iter()
+        .filter(|trailer| keys.is_empty() || keys.iter().any(|key| key.eq_ignore_ascii_case(&trailer.key)))
+        .map(|trailer| if value_only { trailer.value.clone() } else { trailer.to_string() })
+        .collect();
+    Some(match separator {
+        Some(separator) => trailers.join(&separator),
+        None => trailers.iter().map(|trailer| format!("{}\n", trailer)).collect(),
+    })
+}
+
+/// Formats a signature's time, in the signer's own timezone.
+pub(crate) fn format_date(signature: &Signature, mode: DateMode) -> String {
+    const WEEKDAYS: [&str; 7] = ["Thu", "Fri", "Sat", "Sun", "Mon", "Tue", "Wed"];
+    const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+    let local = signature.timestamp + signature.offset_seconds();
+    let days = local.div_euclid(86400);
+    let seconds = local.rem_euclid(86400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (seconds / 3600, seconds % 3600 / 60, seconds % 60);
+    match mode {
+        DateMode::Default => format!(
+            "{} {} {} {:02}:{:02}:{:02} {} {}",
+            WEEKDAYS[days.rem_euclid(7) as usize],
+            MONTHS[month as usize - 1],
+            day,
+            hour,
+            minute,
+            second,
+            year,
+            signature.timezone
+        ),
+        DateMode::Iso => format!(
+            "{}-{:02}-{:02} {:02}:{:02}:{:02} {}",
+            year, month, day, hour, minute, second, signature.timezone
+        ),
+        DateMode::Short => format!("{}-{:02}-{:02}", year, month, day),
+        DateMode::Unix => signature.timestamp.to_string(),
+        DateMode::Relative => format_relative(signature.timestamp),
+    }
+}
+
+/// Describes how long ago a timestamp was, e.g. `3 hours ago`.
+fn format_relative(timestamp: i64) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs() as i64);
+    let diff = now - timestamp;
+    if diff < 0 {
+        return "in the future".to_string();
+    }
+    let plural = |count: i64, unit: &str| match count {
+        1 => format!("1 {} ago", unit),
+        count => format!("{} {}s ago", count, unit),
+    };
+    // Same thresholds as Git: switch units once the count gets unwieldy
+    match diff {
+        d if d < 90 => plural(d, "second"),
+        d if d < 90 * 60 => plural((d + 30) / 60, "minute"),
+        d if d < 36 * 3600 => plural((d + 1800) / 3600, "hour"),
+        d if d < 14 * 86400 => plural((d + 43200) / 86400, "day"),
+        d if d < 70 * 86400 => plural((d + 3 * 86400) / (7 * 86400), "week"),
+        d if d < 365 * 86400 => plural((d + 15 * 86400) / (30 * 86400), "month"),
+        d => plural((d + 183 * 86400) / (365 * 86400), "year"),
+    }
+}
+
+/// Draws the `--graph` columns. Each lane holds the commit it is waiting
+/// for; a lane never appears twice, so branches join as soon as they reach
+/// a commit another lane is already waiting for.
+#[derive(Default)]
+struct Graph {
+    lanes: Vec<String>,
+    /// Width of the graph columns for the commit being printed
+    width: usize,
+}
+
+impl Graph {
+    /// Moves the lanes past a commit that is not shown.
+    fn skip(&mut self, hash: &str, parents: &[String]) {
+        if let Some(column) = self.lanes.iter().position(|lane| lane == hash) {
+            let new_parents: Vec<String> = parents.iter().filter(|p| !self.lanes.contains(p)).cloned().collect();
+            self.lanes.splice(column..=column, new_parents);
+        }
+    }
+
+    /// Prints a commit's lines with the commit marker on the first one and the
+    /// transition to the lanes of its parents on the next.
+    fn print(&mut self, hash: &str, parents: &[String], lines: &[String]) {
+        let column = match self.lanes.iter().position(|lane| lane == hash) {
+            Some(column) => column,
+            None => {
+                self.lanes.push(hash.to_string());
+                self.lanes.len() - 1
+            }
+        };
+        let old_lanes = self.lanes.clone();
+
+        // The commit's lane continues with its parents that no other lane is
+        // waiting for yet; it ends if there are none
+        let new_parents: Vec<String> = parents.iter().filter(|p| !self.lanes.contains(p)).cloned().collect();
+        self.lanes.splice(column..=column, new_parents);
+
+        self.width = old_lanes.len().max(self.lanes.len()) * 2;
+        let width = self.width;
+        let commit_row: String = (0..old_lanes.len()).map(|i| if i == column { "* " } else { "| " }).collect();
+        let transition = self.transition(&old_lanes, column, parents);
+
+        let mut rows = vec![commit_row];
+        rows.extend(transition);
+        let mut lines = lines.iter();
+        for row in &rows {
+            match lines.next() {
+                Some(line) if !line.is_empty() => println!("{:<width$}{}", row, line, width = width),
+                _ => println!("{}", row.trim_end()),
+            }
+        }
+        for line in lines {
+            self.println(true, line);
+        }
+    }
+
+    /// The row connecting the lanes before a commit to the lanes after it,
+    /// or nothing if every lane simply continues straight down.
+    fn transition(&self, old_lanes: &[String], column: usize, parents: &[String]) -> Option<String> {
+        let mut edges = Vec::new();
+        for (from, lane) in old_lanes.iter().enumerate() {
+            if from == column {
+                for parent in parents {
+                    if let Some(to) = self.lanes.iter().position(|l| l == parent) {
+                        edges.push((from, to));
+                    }
+                }
+            } else if let Some(to) = self.lanes.iter().position(|l| l == lane) {
+                edges.push((from, to));
+            }
+        }
+        if edges.iter().all(|(from, to)| from == to) {
+            return None;
+        }
+
+        let width = old_lanes.len().max(self.lanes.len()) * 2;
+        let mut row = vec![' '; width];
+        for (from, to) in edges {
+            let (position, symbol) = match to.cmp(&from) {
+                std::cmp::Ordering::Equal => (from * 2, '|'),
+                std::cmp::Ordering::Greater => (from * 2 + 1, '\\'),
+                std::cmp::Ordering::Less => (from * 2 - 1, '/'),
+            };
+            if row[position] == ' ' {
+                row[position] = symbol;
+            }
+        }
+        Some(row.into_iter().collect())
+    }
+
+    /// Prints a line below the current commit, prefixed by the active lanes
+    /// when drawing the graph.
+    fn println(&self, graph: bool, line: &str) {
+        if !graph {
+            println!("{}", line);
+            return;
+        }
+        let prefix = "| ".repeat(self.lanes.len());
+        println!("{}", format!("{:<width$}{}", prefix, line, width = self.width).trim_end());
+    }
+}
+
+/// The changes a commit introduced relative to its first parent (or, for a
+/// root commit, relative to an empty tree).
+pub(crate) fn commit_changes(repo_path: &Path, tree: &str, parent: Option<&str>) -> io::Result<Vec<FileChange>> {
+    let old = match parent {
+        Some(parent) => read_tree_entries(repo_path, &get_tree_hash(repo_path, parent)?)?,
+        None => FileMap::new(),
+    };
+    let new = read_tree_entries(repo_path, tree)?;
+    changes_between(repo_path, &old, &new)
 }
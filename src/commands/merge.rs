@@ -1,14 +1,55 @@
-use super::commit::{build_commit_content, write_tree, IndexEntry};
-use super::utils::{find_repo_root, get_current_branch, read_head_commit, read_object, update_head, write_object};
+use super::checkout::{switch_tree, TreeOperation};
+use super::commit::{build_commit_content, identity, read_commit, read_index, write_index, write_tree, IndexEntry};
+use super::diff::{diff_lines, DiffLine};
+use super::revision::resolve_commit;
+use super::utils::{
+    blob_object, find_repo_root, get_current_branch, get_tree_hash, is_binary, read_head_commit, read_object,
+    read_tree_entries, read_worktree_file, remove_worktree_file, update_head, write_object, write_worktree_file, MODE_FILE,
+};
 use sha1::{Digest, Sha1};
 use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
 use std::fs;
 use std::io::{self};
 use std::path::{Path, PathBuf};
 
+/// A file that could not be merged automatically, with the blob hash of each
+/// side (`None` where the file does not exist on that side).
+pub(crate) struct Conflict {
+    pub path: PathBuf,
+    pub base: Option<String>,
+    pub ours: Option<String>,
+    pub theirs: Option<String>,
+}
+
+/// The result of merging two trees: the entries for the new index, the
+/// files that still need resolving, and what to put in the working directory
+/// for conflicted text files (the merge with conflict markers).
+struct MergeOutcome {
+    entries: Vec<IndexEntry>,
+    conflicts: Vec<Conflict>,
+    conflict_contents: HashMap<PathBuf, Vec<u8>>,
+}
+
+/// The outcome of merging a single file's contents.
+enum FileMerge {
+    Clean(String),
+    /// The file could not be merged; text files carry their conflict-marked contents.
+    Conflict(Option<Vec<u8>>),
+}
+
 pub fn run(branch_name: &str) -> io::Result<()> {
     let repo_root = find_repo_root()?;
 
+    if merge_head(&repo_root)?.is_some() {
+        return Err(io::Error::other(
+            "You have not concluded your merge (MERGE_HEAD exists).\nPlease resolve the conflicts and commit before merging again.",
+        ));
+    }
+
+    // A merge may need a commit, so make sure there is someone to make it
+    // before touching any files
+    identity(&repo_root, "committer")?;
+
     // --- 1. SETUP: Get commit hashes for both branches ---
     let current_branch = get_current_branch()?.ok_or_else(|| {
         io::Error::other("HEAD is detached, cannot merge")
@@ -18,14 +59,8 @@ pub fn run(branch_name: &str) -> io::Result<()> {
         io::Error::other("Current branch has no commits")
     })?;
 
-    let giver_branch_path = repo_root.join("refs").join("heads").join(branch_name);
-    if !giver_branch_path.exists() {
-        return Err(io::Error::new(
-            io::ErrorKind::NotFound,
-            format!("Branch '{}' not found", branch_name),
-        ));
-    }
-    let giver_hash = fs::read_to_string(giver_branch_path)?.trim().to_string();
+    let giver_hash = resolve_commit(&repo_root, branch_name)
+        .map_err(|e| io::Error::new(io::ErrorKind::NotFound, format!("{} - not something we can merge", e)))?;
 
     // --- 2. FIND ANCESTOR: Find the most recent common ancestor of the two commits ---
     let ancestor_hash = find_common_ancestor(&repo_root, &receiver_hash, &giver_hash)?.ok_or_else(|| {
@@ -39,14 +74,12 @@ pub fn run(branch_name: &str) -> io::Result<()> {
     }
 
     if ancestor_hash == receiver_hash {
-        // This is a fast-forward merge.
-        let branch_ref_path = repo_root.join("refs").join("heads").join(&current_branch);
-        fs::write(branch_ref_path, &giver_hash)?;
+        // This is a fast-forward merge: bring the files up to date, keeping
+        // local changes to files the merge does not touch.
+        let receiver_tree = get_tree_hash(&repo_root, &receiver_hash)?;
+        switch_tree(&repo_root, Some(&receiver_tree), &get_tree_hash(&repo_root, &giver_hash)?, TreeOperation::Merge)?;
+        update_head(&repo_root, &giver_hash, &format!("merge {}: Fast-forward", branch_name))?;
         println!("Fast-forward merge. Updated branch '{}' to '{}'.", current_branch, &giver_hash[..7]);
-        // TODO: Update working directory
-
-
-
         return Ok(());
     }
     
@@ -56,51 +89,185 @@ pub fn run(branch_name: &str) -> io::Result<()> {
     let giver_tree = get_tree_hash(&repo_root, &giver_hash)?;
     let ancestor_tree = get_tree_hash(&repo_root, &ancestor_hash)?;
 
-    let merged_index_entries = merge_trees(&repo_root, &ancestor_tree, &receiver_tree, &giver_tree)?;
+    let outcome = merge_trees(&repo_root, &ancestor_tree, &receiver_tree, &giver_tree, branch_name)?;
 
-    // --- 5. CREATE MERGE COMMIT ---
-    // The new tree from the merged content
-    let merged_tree_hash = write_tree(&repo_root, &merged_index_entries)?;
+    // --- 5. UPDATE INDEX AND WORKING DIRECTORY ---
+    let receiver_entries = read_tree_entries(&repo_root, &receiver_tree)?;
+    update_working_directory(&repo_root, &receiver_entries, &outcome)?;
+    write_index(&repo_root, &outcome.entries)?;
 
     let commit_message = format!("Merge branch '{}' into {}", branch_name, current_branch);
 
+    if !outcome.conflicts.is_empty() {
+        for conflict in &outcome.conflicts {
+            let kind = match (&conflict.ours, &conflict.theirs) {
+                (Some(_), None) | (None, Some(_)) => "modify/delete",
+                _ if conflict.base.is_none() => "add/add",
+                _ => "content",
+            };
+            println!("CONFLICT ({}): Merge conflict in {}", kind, conflict.path.display());
+        }
+        fs::write(repo_root.join("MERGE_HEAD"), &giver_hash)?;
+        fs::write(repo_root.join("MERGE_MSG"), &commit_message)?;
+        write_conflicts(&repo_root, &outcome.conflicts)?;
+        return Err(io::Error::other(
+            "Automatic merge failed; fix conflicts (or run 'rit mergetool') and then commit the result.",
+        ));
+    }
+
+    // --- 6. CREATE MERGE COMMIT ---
+    // The new tree from the merged content
+    let merged_tree_hash = write_tree(&repo_root, &outcome.entries)?;
+
     // Build a commit with TWO parents
     let mut parents = String::new();
     parents.push_str(&format!("parent {}\n", receiver_hash));
     parents.push_str(&format!("parent {}\n", giver_hash));
 
-    let extra_headers = HashMap::new();
-    let commit_content = build_commit_content(&merged_tree_hash, Some(&parents), &commit_message, &extra_headers);
+    let commit_content = build_commit_content(&repo_root, &merged_tree_hash, Some(&parents), &commit_message)?;
 
     let mut hasher = Sha1::new();
     hasher.update(&commit_content);
     let commit_hash = hex::encode(hasher.finalize());
 
     write_object(&repo_root, &commit_hash, commit_content.as_bytes())?;
-    update_head(&repo_root, &commit_hash)?;
+    update_head(&repo_root, &commit_hash, &format!("merge {}: Merge made by a three-way merge", branch_name))?;
 
     println!("Merge complete. Created merge commit {}", &commit_hash[..7]);
-    // TODO: Update working directory and index with merged content
     Ok(())
 }
 
-/// Finds the most recent common ancestor of two commits using breadth-first search.
-fn find_common_ancestor(repo_root: &Path, commit1: &str, commit2: &str) -> io::Result<Option<String>> {
-    let mut parents1 = HashSet::new();
-    let mut queue = VecDeque::new();
-    queue.push_back(commit1.to_string());
-
-    // Collect all ancestors of the first commit
-    while let Some(hash) = queue.pop_front() {
-        if parents1.insert(hash.clone()) {
-            for parent in get_commit_parents(repo_root, &hash)? {
-                queue.push_back(parent);
+/// Brings the working directory in line with the merge result. Refuses to
+/// touch any file that has uncommitted changes, before anything is written.
+fn update_working_directory(
+    repo_root: &Path,
+    receiver_entries: &HashMap<PathBuf, (String, String)>,
+    outcome: &MergeOutcome,
+) -> io::Result<()> {
+    let work_dir = repo_root.parent().unwrap();
+
+    // 1. Work out which files the merge changes relative to our side
+    let mut writes: Vec<(&Path, &str, Vec<u8>)> = Vec::new();
+    for entry in &outcome.entries {
+        if outcome.conflict_contents.contains_key(&entry.path) {
+            continue;
+        }
+        if receiver_entries.get(&entry.path) != Some(&(entry.mode.clone(), entry.sha1.clone())) {
+            writes.push((&entry.path, &entry.mode, read_object(repo_root, &entry.sha1)?));
+        }
+    }
+    for (path, content) in &outcome.conflict_contents {
+        // Conflict markers go into a file with our side's mode
+        let mode = receiver_entries.get(path).map_or(MODE_FILE, |(mode, _)| mode);
+        writes.push((path, mode, content.clone()));
+    }
+    let merged_paths: HashSet<&PathBuf> = outcome.entries.iter().map(|e| &e.path).collect();
+    let removals: Vec<&PathBuf> = receiver_entries
+        .keys()
+        .filter(|path| !merged_paths.contains(path))
+        .collect();
+
+    // 2. Make sure none of them has local changes that would be lost
+    let staged: HashMap<PathBuf, String> = read_index(repo_root)?
+        .into_iter()
+        .map(|entry| (entry.path, entry.sha1))
+        .collect();
+    let mut dirty = Vec::new();
+    for path in writes.iter().map(|(p, _, _)| *p).chain(removals.iter().map(|p| p.as_path())) {
+        if let Some((_, contents)) = read_worktree_file(&work_dir.join(path))? {
+            let (hash, _) = blob_object(&contents);
+            if staged.get(path) != Some(&hash) {
+                dirty.push(path.display().to_string());
             }
         }
     }
+    if !dirty.is_empty() {
+        dirty.sort();
+        return Err(io::Error::other(format!(
+            "error: Your local changes to the following files would be overwritten by merge:\n  {}\n\nPlease commit your changes or stash them before you merge.",
+            dirty.join("\n  ")
+        )));
+    }
+
+    // 3. Apply the changes
+    for (path, mode, content) in writes {
+        write_worktree_file(&work_dir.join(path), mode, &content)?;
+    }
+    for path in removals {
+        remove_worktree_file(work_dir, path)?;
+    }
+    Ok(())
+}
+
+/// Returns the commit being merged in, if a conflicted merge is in progress.
+pub(crate) fn merge_head(repo_root: &Path) -> io::Result<Option<String>> {
+    let merge_head_path = repo_root.join("MERGE_HEAD");
+    if !merge_head_path.exists() {
+        return Ok(None);
+    }
+    Ok(Some(fs::read_to_string(merge_head_path)?.trim().to_string()))
+}
+
+/// Reads the unresolved conflicts of the merge in progress.
+///
+/// Each line of `.rit/MERGE_CONFLICTS` is `<base> <ours> <theirs> <path>`,
+/// with `-` for a side on which the file does not exist.
+pub(crate) fn read_conflicts(repo_root: &Path) -> io::Result<Vec<Conflict>> {
+    let conflicts_path = repo_root.join("MERGE_CONFLICTS");
+    if !conflicts_path.exists() {
+        return Ok(Vec::new());
+    }
+    let side = |hash: &str| (hash != "-").then(|| hash.to_string());
+    Ok(fs::read_to_string(conflicts_path)?
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.splitn(4, ' ').collect();
+            (parts.len() == 4).then(|| Conflict {
+                path: PathBuf::from(parts[3]),
+                base: side(parts[0]),
+                ours: side(parts[1]),
+                theirs: side(parts[2]),
+            })
+        })
+        .collect())
+}
+
+fn write_conflicts(repo_root: &Path, conflicts: &[Conflict]) -> io::Result<()> {
+    let side = |hash: &Option<String>| hash.clone().unwrap_or_else(|| "-".to_string());
+    let content: String = conflicts
+        .iter()
+        .map(|c| format!("{} {} {} {}\n", side(&c.base), side(&c.ours), side(&c.theirs), c.path.display()))
+        .collect();
+    fs::write(repo_root.join("MERGE_CONFLICTS"), content)
+}
+
+/// Marks a conflicted path as resolved (called when it is added or removed).
+pub(crate) fn mark_resolved(repo_root: &Path, path: &Path) -> io::Result<()> {
+    let conflicts = read_conflicts(repo_root)?;
+    if conflicts.iter().any(|c| c.path == path) {
+        let remaining: Vec<Conflict> = conflicts.into_iter().filter(|c| c.path != path).collect();
+        write_conflicts(repo_root, &remaining)?;
+    }
+    Ok(())
+}
+
+/// Removes all merge state once the merge commit has been created.
+pub(crate) fn clear_merge_state(repo_root: &Path) -> io::Result<()> {
+    for name in ["MERGE_HEAD", "MERGE_MSG", "MERGE_CONFLICTS"] {
+        let path = repo_root.join(name);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Finds the most recent common ancestor of two commits using breadth-first search.
+fn find_common_ancestor(repo_root: &Path, commit1: &str, commit2: &str) -> io::Result<Option<String>> {
+    let parents1 = ancestors(repo_root, commit1)?;
 
     // Traverse ancestors of the second commit until a match is found
-    queue.clear();
+    let mut queue = VecDeque::new();
     queue.push_back(commit2.to_string());
     let mut visited2 = HashSet::new();
 
@@ -118,68 +285,57 @@ fn find_common_ancestor(repo_root: &Path, commit1: &str, commit2: &str) -> io::R
     Ok(None)
 }
 
-/// Reads a commit object and returns a list of its parent hashes.
-fn get_commit_parents(repo_root: &Path, hash: &str) -> io::Result<Vec<String>> {
-    let commit_data = read_object(repo_root, hash)?;
-    let content = String::from_utf8_lossy(&commit_data);
-    Ok(content
-        .lines()
-        .filter(|line| line.starts_with("parent "))
-        .map(|line| line[7..].to_string())
-        .collect())
+/// Collects a commit and all of its ancestors.
+pub(crate) fn ancestors(repo_root: &Path, commit: &str) -> io::Result<HashSet<String>> {
+    let mut seen = HashSet::new();
+    let mut queue = VecDeque::new();
+    queue.push_back(commit.to_string());
+
+    while let Some(hash) = queue.pop_front() {
+        if seen.insert(hash.clone()) {
+            for parent in get_commit_parents(repo_root, &hash)? {
+                queue.push_back(parent);
+            }
+        }
+    }
+    Ok(seen)
 }
 
-/// Reads a commit object and returns its root tree hash.
-fn get_tree_hash(repo_root: &Path, hash: &str) -> io::Result<String> {
-    let commit_data = read_object(repo_root, hash)?;
-    let content = String::from_utf8_lossy(&commit_data);
-    content
-        .lines()
-        .find(|line| line.starts_with("tree "))
-        .map(|line| line[5..].to_string())
-        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Commit missing tree hash"))
+/// Reads a commit object and returns a list of its parent hashes.
+pub(crate) fn get_commit_parents(repo_root: &Path, hash: &str) -> io::Result<Vec<String>> {
+    Ok(read_commit(repo_root, hash)?.parents)
 }
 
-/// Parses a tree object into a map of {filename -> (mode, hash)}.
-fn read_tree_entries(repo_root: &Path, tree_hash: &str) -> io::Result<HashMap<PathBuf, (String, String)>> {
-    let tree_data = read_object(repo_root, tree_hash)?;
-    let mut entries = HashMap::new();
-    let mut pos = 0;
-    while pos < tree_data.len() {
-        let mut end = pos;
-        while tree_data[end] != 0 { end += 1; }
-        let entry_str = String::from_utf8_lossy(&tree_data[pos..end]);
-        let (mode, filename) = entry_str.split_once(' ').unwrap();
-        let sha_start = end + 1;
-        let sha_end = sha_start + 20;
-        let sha_bytes = &tree_data[sha_start..sha_end];
-        let sha1 = hex::encode(sha_bytes);
-        entries.insert(PathBuf::from(filename), (mode.to_string(), sha1));
-        pos = sha_end;
-    }
-    Ok(entries)
-}
-
-/// Performs a simplified three-way merge of trees.
-/// NOTE: This is a simplified implementation. It merges file lists but does not handle
-/// content-level merges or recursive directory merges. It will error on conflicts.
+/// Performs a three-way merge of trees.
+///
+/// Files changed on only one side are taken from that side; files changed on
+/// both sides are merged line by line. Anything that cannot be merged is
+/// recorded as a conflict, keeping our version in the index.
 fn merge_trees(
     repo_root: &Path,
     ancestor_tree: &str,
     receiver_tree: &str, // Our current branch (e.g., main)
     giver_tree: &str,    // The branch being merged in (e.g., feature)
-) -> io::Result<Vec<IndexEntry>> {
+    giver_label: &str,
+) -> io::Result<MergeOutcome> {
     let ancestor_entries = read_tree_entries(repo_root, ancestor_tree)?;
     let receiver_entries = read_tree_entries(repo_root, receiver_tree)?;
     let giver_entries = read_tree_entries(repo_root, giver_tree)?;
 
     let mut merged_entries = BTreeMap::new();
+    let mut conflicts = Vec::new();
+    let mut conflict_contents = HashMap::new();
 
     // Union of all file paths across the three trees
-    let mut all_paths = HashSet::new();
-    all_paths.extend(ancestor_entries.keys().cloned());
-    all_paths.extend(receiver_entries.keys().cloned());
-    all_paths.extend(giver_entries.keys().cloned());
+    let mut all_paths: Vec<PathBuf> = ancestor_entries
+        .keys()
+        .chain(receiver_entries.keys())
+        .chain(giver_entries.keys())
+        .cloned()
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    all_paths.sort();
 
     for path in all_paths {
         let ancestor = ancestor_entries.get(&path);
@@ -199,18 +355,40 @@ fn merge_trees(
             (Some(a), Some(r), None) if a == r => {} // Giver deleted, receiver unchanged -> delete
             (None, Some(r), Some(g)) if r == g => { merged_entries.insert(path, r.clone()); } // Both added same file
 
-            // CONFLICTS
-            (Some(_), Some(r), Some(g)) if r.1 != g.1 => { // Modified differently
-                 return Err(io::Error::other(format!("Merge conflict in {}", path.display())));
-            }
-            (None, Some(_), Some(_)) => { // Both added same file with different content
-                return Err(io::Error::other(format!("Merge conflict: both added {}", path.display())));
-            }
-            (Some(_), Some(_), None) => { // Receiver modified, giver deleted
-                return Err(io::Error::other(format!("Merge conflict: {} modified and deleted", path.display())));
+            // Changed (or added) differently on both sides: try a line-level merge
+            (a, Some(r), Some(g)) if r.1 != g.1 => {
+                let base = a.map(|a| a.1.as_str());
+                match merge_file_contents(repo_root, &path, base, &r.1, &g.1, giver_label)? {
+                    FileMerge::Clean(merged_hash) => {
+                        merged_entries.insert(path, (r.0.clone(), merged_hash));
+                    }
+                    FileMerge::Conflict(content) => {
+                        if let Some(content) = content {
+                            conflict_contents.insert(path.clone(), content);
+                        }
+                        conflicts.push(Conflict {
+                            path: path.clone(),
+                            base: base.map(str::to_string),
+                            ours: Some(r.1.clone()),
+                            theirs: Some(g.1.clone()),
+                        });
+                        merged_entries.insert(path, r.clone());
+                    }
+                }
             }
-            (Some(_), None, Some(_)) => { // Giver modified, receiver deleted
-                return Err(io::Error::other(format!("Merge conflict: {} modified and deleted", path.display())));
+
+            // CONFLICTS: modified on one side, deleted on the other. The
+            // modified version is kept so that it can be inspected.
+            (Some(a), r, g) if r.is_none() || g.is_none() => {
+                conflicts.push(Conflict {
+                    path: path.clone(),
+                    base: Some(a.1.clone()),
+                    ours: r.map(|r| r.1.clone()),
+                    theirs: g.map(|g| g.1.clone()),
+                });
+                if let Some(kept) = r.or(g) {
+                    merged_entries.insert(path, kept.clone());
+                }
             }
 
             // Default to receiver's version if logic is incomplete
@@ -218,9 +396,165 @@ fn merge_trees(
             _ => {}
         }
     }
-    
+
     // Convert the merged BTreeMap back into a Vec<IndexEntry>
-    Ok(merged_entries.into_iter().map(|(path, (mode, sha1))| {
+    let entries = merged_entries.into_iter().map(|(path, (mode, sha1))| {
         IndexEntry { mode, sha1, path }
-    }).collect())
-}
\ No newline at end of file
+    }).collect();
+
+    Ok(MergeOutcome { entries, conflicts, conflict_contents })
+}
+
+/// Merges the contents of a file changed on both sides and writes the result
+/// as a new blob. Binary files are never line-merged: they are reported as a
+/// conflict on the file as a whole.
+fn merge_file_contents(
+    repo_root: &Path,
+    path: &Path,
+    ancestor_sha: Option<&str>,
+    receiver_sha: &str,
+    giver_sha: &str,
+    giver_label: &str,
+) -> io::Result<FileMerge> {
+    let ancestor = match ancestor_sha {
+        Some(sha) => read_object(repo_root, sha)?,
+        None => Vec::new(),
+    };
+    let receiver = read_object(repo_root, receiver_sha)?;
+    let giver = read_object(repo_root, giver_sha)?;
+
+    if is_binary(&ancestor) || is_binary(&receiver) || is_binary(&giver) {
+        println!("warning: Cannot merge binary file {}", path.display());
+        return Ok(FileMerge::Conflict(None));
+    }
+
+    println!("Auto-merging {}", path.display());
+    let (merged, clean) = merge_lines(
+        &String::from_utf8_lossy(&ancestor),
+        &String::from_utf8_lossy(&receiver),
+        &String::from_utf8_lossy(&giver),
+        giver_label,
+    );
+    if !clean {
+        return Ok(FileMerge::Conflict(Some(merged.into_bytes())));
+    }
+
+    let (hash, blob_data) = blob_object(merged.as_bytes());
+    write_object(repo_root, &hash, &blob_data)?;
+    Ok(FileMerge::Clean(hash))
+}
+
+/// A change relative to the ancestor: replace ancestor lines `start..end` with `lines`.
+#[derive(PartialEq)]
+struct Hunk<'a> {
+    start: usize,
+    end: usize,
+    lines: Vec<&'a str>,
+}
+
+/// Turns a line diff against the ancestor into a list of replacement hunks.
+fn hunks<'a>(ancestor: &'a str, side: &'a str) -> Vec<Hunk<'a>> {
+    let mut hunks = Vec::new();
+    let mut current: Option<Hunk> = None;
+    let mut pos = 0;
+
+    for line in diff_lines(ancestor, side) {
+        match line {
+            DiffLine::Equal(_) => {
+                hunks.extend(current.take());
+                pos += 1;
+            }
+            DiffLine::Delete(_) => {
+                current.get_or_insert(Hunk { start: pos, end: pos, lines: Vec::new() }).end += 1;
+                pos += 1;
+            }
+            DiffLine::Insert(text) => {
+                current.get_or_insert(Hunk { start: pos, end: pos, lines: Vec::new() }).lines.push(text);
+            }
+        }
+    }
+    hunks.extend(current);
+    hunks
+}
+
+/// Applies a run of hunks to the ancestor lines `start..end`.
+fn apply_hunks<'a>(ancestor_lines: &[&'a str], start: usize, end: usize, hunks: &[Hunk<'a>]) -> Vec<&'a str> {
+    let mut lines = Vec::new();
+    let mut pos = start;
+    for hunk in hunks {
+        lines.extend(&ancestor_lines[pos..hunk.start]);
+        lines.extend(&hunk.lines);
+        pos = hunk.end;
+    }
+    lines.extend(&ancestor_lines[pos..end]);
+    lines
+}
+
+/// Three-way merges text line by line. Where both sides changed the same (or
+/// adjacent) lines differently, the region is wrapped in conflict markers and
+/// the returned flag is `false`.
+fn merge_lines(ancestor: &str, receiver: &str, giver: &str, giver_label: &str) -> (String, bool) {
+    let ancestor_lines: Vec<&str> = ancestor.lines().collect();
+    let receiver_hunks = hunks(ancestor, receiver);
+    let giver_hunks = hunks(ancestor, giver);
+    let closing_marker = format!(">>>>>>> {}", giver_label);
+
+    let mut merged: Vec<&str> = Vec::new();
+    let mut clean = true;
+    let mut pos = 0;
+    let (mut i, mut j) = (0, 0);
+
+    loop {
+        let (start, end, ours, theirs) = match (receiver_hunks.get(i), giver_hunks.get(j)) {
+            (Some(r), Some(g)) if r.start <= g.end && g.start <= r.end => {
+                // Grow the region until no further hunk from either side touches it
+                let (first_i, first_j) = (i, j);
+                let start = r.start.min(g.start);
+                let mut end = r.end.max(g.end);
+                i += 1;
+                j += 1;
+                loop {
+                    if let Some(h) = receiver_hunks.get(i).filter(|h| h.start <= end) {
+                        end = end.max(h.end);
+                        i += 1;
+                    } else if let Some(h) = giver_hunks.get(j).filter(|h| h.start <= end) {
+                        end = end.max(h.end);
+                        j += 1;
+                    } else {
+                        break;
+                    }
+                }
+                let ours = apply_hunks(&ancestor_lines, start, end, &receiver_hunks[first_i..i]);
+                let theirs = apply_hunks(&ancestor_lines, start, end, &giver_hunks[first_j..j]);
+                (start, end, ours, Some(theirs))
+            }
+            (Some(r), Some(g)) if r.start < g.start => { i += 1; (r.start, r.end, r.lines.clone(), None) }
+            (Some(_), Some(g)) => { j += 1; (g.start, g.end, g.lines.clone(), None) }
+            (Some(r), None) => { i += 1; (r.start, r.end, r.lines.clone(), None) }
+            (None, Some(g)) => { j += 1; (g.start, g.end, g.lines.clone(), None) }
+            (None, None) => break,
+        };
+
+        merged.extend(&ancestor_lines[pos..start]);
+        match theirs {
+            // Overlapping changes only merge cleanly if they are identical
+            Some(theirs) if theirs != ours => {
+                clean = false;
+                merged.push("<<<<<<< HEAD");
+                merged.extend(ours);
+                merged.push("=======");
+                merged.extend(theirs);
+                merged.push(&closing_marker);
+            }
+            _ => merged.extend(ours),
+        }
+        pos = end;
+    }
+    merged.extend(&ancestor_lines[pos..]);
+
+    let mut result = merged.join("\n");
+    if !merged.is_empty() && (receiver.ends_with('\n') || giver.ends_with('\n') || !clean) {
+        result.push('\n');
+    }
+    (result, clean)
+}
@@ -0,0 +1,63 @@
+use super::commit::read_index;
+use super::config::read_config_file;
+use super::difftool::{run_tool, tool_command, TempDir};
+use super::merge::{mark_resolved, read_conflicts};
+use super::utils::{find_repo_root, read_object};
+use std::io;
+
+pub fn run(paths: &[String], tool: Option<&str>) -> io::Result<()> {
+    let repo_root = find_repo_root()?;
+    let work_dir = repo_root.parent().unwrap();
+    let config = read_config_file(&repo_root)?;
+
+    let tool_name = tool
+        .map(str::to_string)
+        .or_else(|| config.get("merge.tool").cloned())
+        .ok_or_else(|| io::Error::other("No merge tool configured. Set one with: rit config merge.tool <tool>"))?;
+    let command = tool_command(&config, "mergetool", &tool_name)?;
+
+    let conflicts: Vec<_> = read_conflicts(&repo_root)?
+        .into_iter()
+        .filter(|c| paths.is_empty() || paths.iter().any(|p| c.path.starts_with(p)))
+        .collect();
+    if conflicts.is_empty() {
+        println!("No files need merging");
+        return Ok(());
+    }
+
+    let temp_dir = TempDir::new("mergetool")?;
+    for conflict in &conflicts {
+        // Materialize every side; a side the file is missing from stays empty
+        let blob = |hash: &Option<String>| match hash {
+            Some(hash) => read_object(&repo_root, hash),
+            None => Ok(Vec::new()),
+        };
+        let base = temp_dir.materialize(&conflict.path, "BASE", &blob(&conflict.base)?)?;
+        let local = temp_dir.materialize(&conflict.path, "LOCAL", &blob(&conflict.ours)?)?;
+        let remote = temp_dir.materialize(&conflict.path, "REMOTE", &blob(&conflict.theirs)?)?;
+        let merged = work_dir.join(&conflict.path);
+
+        println!("Merging: '{}'", conflict.path.display());
+        let success = run_tool(
+            &command,
+            &[("BASE", &base), ("LOCAL", &local), ("REMOTE", &remote), ("MERGED", &merged)],
+        )?;
+        if !success {
+            return Err(io::Error::other(format!(
+                "merge of {} failed; the conflict is still unresolved",
+                conflict.path.display()
+            )));
+        }
+
+        // The tool succeeded: stage its result (or the deletion) as the resolution
+        if merged.exists() {
+            super::add::run(&merged.to_string_lossy())?;
+        } else if read_index(&repo_root)?.iter().any(|entry| entry.path == conflict.path) {
+            super::remove::run(&conflict.path.to_string_lossy(), true)?;
+        } else {
+            // Already gone from the index too: there is nothing to unstage
+            mark_resolved(&repo_root, &conflict.path)?;
+        }
+    }
+    Ok(())
+}
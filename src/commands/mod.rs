@@ -11,4 +11,23 @@ pub(crate) mod status;
 pub(crate) mod config;
 pub (crate) mod remove;
 pub(crate) mod diff;
-pub(crate) mod merge;
\ No newline at end of file
+pub(crate) mod merge;
+pub(crate) mod difftool;
+pub(crate) mod mergetool;
+pub(crate) mod show;
+pub(crate) mod cat_file;
+pub(crate) mod hash_object;
+pub(crate) mod write_tree;
+pub(crate) mod commit_tree;
+pub(crate) mod update_ref;
+pub(crate) mod rev_parse;
+pub(crate) mod revision;
+pub(crate) mod tag;
+pub(crate) mod describe;
+pub(crate) mod check_ref_format;
+pub(crate) mod reflog;
+pub(crate) mod pack_refs;
+pub(crate) mod symbolic_ref;
+pub(crate) mod switch;
+pub(crate) mod interpret_trailers;
+pub(crate) mod date;
\ No newline at end of file
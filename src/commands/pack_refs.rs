@@ -0,0 +1,43 @@
+use super::revision::peel_tags;
+use super::utils::{
+    find_repo_root, loose_refs, object_type, read_packed_refs, read_symbolic_ref, remove_empty_ref_dirs,
+    write_packed_refs, PackedRef,
+};
+use std::fs;
+use std::io;
+
+/// Moves loose refs into `.rit/packed-refs`. Tags are always packed, and
+/// branches too with `all`; annotated tags get a peeled `^` line. Unless
+/// `no_prune` is set, the loose files are removed afterwards.
+pub fn run(all: bool, no_prune: bool) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+
+    let mut packed = read_packed_refs(&repo_path)?;
+    let loose: Vec<(String, String)> = loose_refs(&repo_path, "refs")?
+        .into_iter()
+        .filter(|(name, _)| all || name.starts_with("refs/tags/"))
+        .filter(|(name, _)| !matches!(read_symbolic_ref(&repo_path, name), Ok(Some(_))))
+        .collect();
+
+    for (name, hash) in &loose {
+        let peeled = match object_type(&repo_path, hash)?.as_str() {
+            "tag" => Some(peel_tags(&repo_path, hash)?),
+            _ => None,
+        };
+        packed.retain(|p| &p.name != name);
+        packed.push(PackedRef { name: name.clone(), hash: hash.clone(), peeled });
+    }
+    write_packed_refs(&repo_path, &packed)?;
+
+    if !no_prune {
+        for (name, hash) in &loose {
+            let path = repo_path.join(name);
+            // Leave refs alone that moved while we were packing them
+            if fs::read_to_string(&path)?.trim() == hash {
+                fs::remove_file(&path)?;
+                remove_empty_ref_dirs(&repo_path, &path);
+            }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,270 @@
+use super::commit::{identity, Signature};
+use super::date::parse_date;
+use super::log::{format_date, DateMode};
+use super::utils::{check_ref_name, find_repo_root, get_current_branch, list_refs};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// The hash recorded as the old value when a ref is created.
+pub(crate) const NULL_HASH: &str = "0000000000000000000000000000000000000000";
+
+/// How long `rit reflog expire` keeps entries by default.
+const DEFAULT_EXPIRY: &str = "90 days ago";
+
+/// One line of a reflog: a ref moving from `old` to `new`, who moved it
+/// and when, and why (`commit: msg`, `checkout: moving from a to b`, ...).
+#[derive(Debug, Clone)]
+pub(crate) struct ReflogEntry {
+    pub old: String,
+    pub new: String,
+    pub who: Signature,
+    pub message: String,
+}
+
+impl ReflogEntry {
+    fn parse(line: &str) -> Option<ReflogEntry> {
+        let (info, message) = line.split_once('\t').unwrap_or((line, ""));
+        let mut fields = info.splitn(3, ' ');
+        Some(ReflogEntry {
+            old: fields.next()?.to_string(),
+            new: fields.next()?.to_string(),
+            who: Signature::parse(fields.next().unwrap_or("")),
+            message: message.to_string(),
+        })
+    }
+
+    fn to_line(&self) -> String {
+        format!("{} {} {}\t{}\n", self.old, self.new, self.who, self.message)
+    }
+}
+
+/// Where the reflog of `refname` lives. The name is checked first, so a
+/// reflog can never be read or written outside of `.rit/logs`.
+fn reflog_path(repo_path: &Path, refname: &str) -> io::Result<PathBuf> {
+    check_ref_name(refname)?;
+    let relative = Path::new(refname);
+    if !relative.components().all(|component| matches!(component, Component::Normal(_))) {
+        return Err(io::Error::other(format!("refusing reflog outside of logs/: '{}'", refname)));
+    }
+    Ok(repo_path.join("logs").join(relative))
+}
+
+/// Whether updates to `refname` are recorded: HEAD and branches always are,
+/// other refs only once they have a log.
+fn logs_updates(repo_path: &Path, refname: &str) -> bool {
+    refname == "HEAD" || refname.starts_with("refs/heads/") || reflog_path(repo_path, refname).is_ok_and(|path| path.is_file())
+}
+
+/// Appends an entry to a ref's reflog, creating the log if needed.
+pub(crate) fn append_reflog(repo_path: &Path, refname: &str, old: &str, new: &str, message: &str) -> io::Result<()> {
+    if !logs_updates(repo_path, refname) {
+        return Ok(());
+    }
+    let path = reflog_path(repo_path, refname)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    // Moving a ref shouldn't fail for want of an identity, so the log
+    // makes do without one
+    let who = identity(repo_path, "committer").unwrap_or_else(|_| Signature::now("unknown", "unknown"));
+    let entry = ReflogEntry {
+        old: if old.is_empty() { NULL_HASH } else { old }.to_string(),
+        new: new.to_string(),
+        who,
+        // Messages are kept to one line so every entry stays one line
+        message: message.lines().next().unwrap_or("").to_string(),
+    };
+    OpenOptions::new().create(true).append(true).open(path)?.write_all(entry.to_line().as_bytes())
+}
+
+/// Reads a ref's reflog, oldest entry first.
+pub(crate) fn read_reflog(repo_path: &Path, refname: &str) -> io::Result<Vec<ReflogEntry>> {
+    let path = reflog_path(repo_path, refname)?;
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    Ok(fs::read_to_string(path)?.lines().filter_map(ReflogEntry::parse).collect())
+}
+
+fn write_reflog(repo_path: &Path, refname: &str, entries: &[ReflogEntry]) -> io::Result<()> {
+    let content: String = entries.iter().map(ReflogEntry::to_line).collect();
+    fs::write(reflog_path(repo_path, refname)?, content)
+}
+
+/// Removes the reflog of a deleted ref, and any directories it leaves empty.
+pub(crate) fn delete_reflog(repo_path: &Path, refname: &str) -> io::Result<()> {
+    let logs_dir = repo_path.join("logs");
+    let path = reflog_path(repo_path, refname)?;
+    if path.is_file() {
+        fs::remove_file(&path)?;
+    }
+    for dir in path.ancestors().skip(1) {
+        if dir == logs_dir || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// Moves a ref's reflog along with a renamed ref.
+pub(crate) fn rename_reflog(repo_path: &Path, old: &str, new: &str) -> io::Result<()> {
+    let old_path = reflog_path(repo_path, old)?;
+    if !old_path.is_file() {
+        return Ok(());
+    }
+    let new_path = reflog_path(repo_path, new)?;
+    if let Some(parent) = new_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::rename(&old_path, &new_path)?;
+    delete_reflog(repo_path, old)
+}
+
+/// The full ref name a reflog argument such as `main` or `HEAD` refers to.
+fn reflog_ref(repo_path: &Path, name: &str) -> io::Result<String> {
+    check_ref_name(name)?;
+    let candidates = [name.to_string(), format!("refs/heads/{}", name), format!("refs/tags/{}", name)];
+    candidates
+        .into_iter()
+        .find(|candidate| reflog_path(repo_path, candidate).is_ok_and(|path| path.is_file()))
+        .ok_or_else(|| io::Error::other(format!("no reflog for '{}'", name)))
+}
+
+/// `rit reflog [show] [<ref>]`: the ref's history, newest first.
+pub fn show(refname: Option<&str>, max_count: Option<usize>) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    let name = refname.unwrap_or("HEAD");
+    let entries = read_reflog(&repo_path, &reflog_ref(&repo_path, name)?)?;
+
+    let label = name.trim_start_matches("refs/heads/");
+    for (n, entry) in entries.iter().rev().enumerate().take(max_count.unwrap_or(usize::MAX)) {
+        println!("{} {}@{{{}}}: {}", &entry.new[..7], label, n, entry.message);
+    }
+    Ok(())
+}
+
+/// `rit reflog expire`: drops entries older than `expire` (90 days by
+/// default) from the given reflogs, or from all of them with `all`.
+pub fn expire(refs: &[String], all: bool, expire: Option<&str>) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    let cutoff = match expire.unwrap_or(DEFAULT_EXPIRY) {
+        "all" => i64::MAX,
+        "never" => i64::MIN,
+        date => parse_date(date).map_err(io::Error::other)?,
+    };
+
+    let refnames = match all {
+        true => all_reflogs(&repo_path)?,
+        false if refs.is_empty() => return Err(io::Error::other("no reflog specified to expire")),
+        false => refs.iter().map(|name| reflog_ref(&repo_path, name)).collect::<io::Result<_>>()?,
+    };
+    for refname in refnames {
+        let mut entries = read_reflog(&repo_path, &refname)?;
+        entries.retain(|entry| entry.who.timestamp >= cutoff);
+        write_reflog(&repo_path, &refname, &entries)?;
+    }
+    Ok(())
+}
+
+/// `rit reflog delete <ref>@{<n>}...`: removes single entries.
+pub fn delete(entries: &[String]) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    if entries.is_empty() {
+        return Err(io::Error::other("no reflog entry specified to delete"));
+    }
+
+    // Delete from the highest index down so earlier indices stay valid
+    let mut targets = Vec::new();
+    for spec in entries {
+        let (name, n) = spec
+            .strip_suffix('}')
+            .and_then(|s| s.rsplit_once("@{"))
+            .and_then(|(name, n)| Some((name, n.parse::<usize>().ok()?)))
+            .ok_or_else(|| io::Error::other(format!("not a reflog entry: '{}'", spec)))?;
+        let refname = match name {
+            "" => current_ref(),
+            name => name.to_string(),
+        };
+        targets.push((reflog_ref(&repo_path, &refname)?, n, spec));
+    }
+    targets.sort_by_key(|target| std::cmp::Reverse(target.1));
+
+    for (refname, n, spec) in targets {
+        let mut log = read_reflog(&repo_path, &refname)?;
+        if n >= log.len() {
+            return Err(io::Error::other(format!("reflog entry '{}' not found", spec)));
+        }
+        let index = log.len() - 1 - n;
+        log.remove(index);
+        write_reflog(&repo_path, &refname, &log)?;
+    }
+    Ok(())
+}
+
+/// Every ref that has a reflog.
+fn all_reflogs(repo_path: &Path) -> io::Result<Vec<String>> {
+    let mut refnames: Vec<String> = ["heads", "tags"]
+        .into_iter()
+        .map(|kind| list_refs(repo_path, kind).map(|refs| (kind, refs)))
+        .collect::<io::Result<Vec<_>>>()?
+        .into_iter()
+        .flat_map(|(kind, refs)| refs.into_iter().map(move |(name, _)| format!("refs/{}/{}", kind, name)))
+        .collect();
+    refnames.insert(0, "HEAD".to_string());
+    refnames.retain(|name| reflog_path(repo_path, name).is_ok_and(|path| path.is_file()));
+    Ok(refnames)
+}
+
+/// The ref whose reflog a bare `@{n}` reads: the current branch, or HEAD
+/// when detached.
+pub(crate) fn current_ref() -> String {
+    match get_current_branch() {
+        Ok(Some(branch)) => format!("refs/heads/{}", branch),
+        _ => "HEAD".to_string(),
+    }
+}
+
+/// The value a ref had `n` updates ago; `@{0}` is its current value.
+pub(crate) fn reflog_entry(repo_path: &Path, refname: &str, n: usize) -> io::Result<String> {
+    let entries = read_reflog(repo_path, refname)?;
+    if entries.is_empty() {
+        return Err(io::Error::other(format!("no reflog for '{}'", refname)));
+    }
+    entries
+        .iter()
+        .rev()
+        .nth(n)
+        .map(|entry| entry.new.clone())
+        .ok_or_else(|| io::Error::other(format!("log for '{}' only has {} entries", refname, entries.len())))
+}
+
+/// The value a ref had at a point in time, for `main@{yesterday}`.
+pub(crate) fn reflog_at(repo_path: &Path, refname: &str, when: i64) -> io::Result<String> {
+    let entries = read_reflog(repo_path, refname)?;
+    let Some(first) = entries.first() else {
+        return Err(io::Error::other(format!("no reflog for '{}'", refname)));
+    };
+    if let Some(entry) = entries.iter().rev().find(|entry| entry.who.timestamp <= when) {
+        return Ok(entry.new.clone());
+    }
+    // Before the log starts, the best answer is the value the first entry replaced
+    let start = format_date(&first.who, DateMode::Default);
+    if first.old != NULL_HASH {
+        eprintln!("warning: log for '{}' only goes back to {}", refname, start);
+        return Ok(first.old.clone());
+    }
+    Err(io::Error::other(format!("log for '{}' only goes back to {}", refname, start)))
+}
+
+/// The branch that was checked out `n` switches ago, from the
+/// `checkout: moving from <a> to <b>` entries in HEAD's reflog.
+pub(crate) fn previous_branch(repo_path: &Path, n: usize) -> io::Result<String> {
+    read_reflog(repo_path, "HEAD")?
+        .iter()
+        .rev()
+        .filter_map(|entry| entry.message.strip_prefix("checkout: moving from "))
+        .filter_map(|moves| moves.split_once(" to ").map(|(from, _)| from.to_string()))
+        .nth(n.saturating_sub(1))
+        .ok_or_else(|| io::Error::other(format!("@{{-{}}}: not enough branch switches in the reflog", n)))
+}
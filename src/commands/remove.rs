@@ -1,8 +1,10 @@
 // In src/commands/remove.rs
 
+use super::merge::mark_resolved;
 use super::utils::find_repo_root;
 use std::fs;
 use std::io::{self};
+use std::path::Path;
 
 pub fn run(path_str: &str, cached: bool) -> io::Result<()> {
     let repo_root = find_repo_root()?;
@@ -50,6 +52,7 @@ pub fn run(path_str: &str, cached: bool) -> io::Result<()> {
         new_entries.join("\n") + "\n"
     };
     fs::write(&index_path, new_content)?;
+    mark_resolved(&repo_root, Path::new(path_str))?;
 
     // 4. If not --cached, remove the file from the working directory.
     if !cached {
@@ -0,0 +1,49 @@
+use super::revision::resolve_revision;
+use super::utils::{find_repo_root, get_current_branch};
+use std::io;
+
+/// Options for `rit rev-parse`.
+#[derive(Debug, Default)]
+pub struct RevParseOptions {
+    /// Abbreviate hashes to this many characters
+    pub short: Option<usize>,
+    /// Require exactly one revision that resolves
+    pub verify: bool,
+    /// Print the branch name a ref points at instead of a hash
+    pub abbrev_ref: bool,
+    pub show_toplevel: bool,
+    pub git_dir: bool,
+}
+
+pub fn run(revisions: &[String], options: &RevParseOptions) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    if options.show_toplevel {
+        println!("{}", repo_path.parent().unwrap().display());
+    }
+    if options.git_dir {
+        println!("{}", repo_path.display());
+    }
+    if options.verify && revisions.len() != 1 {
+        return Err(io::Error::other("Needed a single revision"));
+    }
+
+    for revision in revisions {
+        if options.abbrev_ref {
+            // HEAD reads as its branch; branch names are already abbreviated
+            match revision.as_str() {
+                "HEAD" => println!("{}", get_current_branch()?.unwrap_or_else(|| "HEAD".to_string())),
+                name => {
+                    resolve_revision(&repo_path, name)?;
+                    println!("{}", name.trim_start_matches("refs/heads/").trim_start_matches("refs/tags/"));
+                }
+            }
+            continue;
+        }
+        let hash = resolve_revision(&repo_path, revision)?;
+        match options.short {
+            Some(length) => println!("{}", &hash[..length.clamp(4, hash.len())]),
+            None => println!("{}", hash),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,246 @@
+use super::commit::{read_commit, read_index};
+use super::date::parse_date;
+use super::reflog::{current_ref, previous_branch, reflog_at, reflog_entry};
+use super::utils::{
+    check_ref_format, find_tree_entry, is_special_ref, object_type, read_head_commit, read_object, read_ref, ref_exists,
+};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Resolves a revision expression to an object hash.
+///
+/// Understands `HEAD`, branch and tag names, full refs such as
+/// `refs/heads/main`, unique hash prefixes of at least 4 characters,
+/// `@{-n}` (the n-th previously checked out branch), `<ref>@{n}` reflog
+/// entries, `<ref>@{<date>}` (e.g. `main@{yesterday}`), and any chain of
+/// `~n`, `^n` and `^{type}` suffixes, e.g. `HEAD~3`, `main^2` or
+/// `v1.0^{tree}`. `<rev>:<path>` names a file or directory inside a
+/// commit, and `:<path>` the staged version of a file.
+pub(crate) fn resolve_revision(repo_path: &Path, spec: &str) -> io::Result<String> {
+    if let Some((rev, path)) = split_path(spec) {
+        if rev.is_empty() {
+            return read_index(repo_path)?
+                .into_iter()
+                .find(|entry| entry.path == Path::new(path))
+                .map(|entry| entry.sha1)
+                .ok_or_else(|| io::Error::other(format!("path '{}' is not in the index", path)));
+        }
+        let tree = peel(repo_path, &resolve_revision(repo_path, rev)?, "tree")?;
+        return find_tree_entry(repo_path, &tree, Path::new(path))?
+            .map(|(_, hash)| hash)
+            .ok_or_else(|| io::Error::other(format!("path '{}' does not exist in '{}'", path, rev)));
+    }
+
+    let base_end = suffix_start(spec);
+    let mut hash = resolve_base(repo_path, &spec[..base_end])?;
+
+    let mut rest = &spec[base_end..];
+    while !rest.is_empty() {
+        let (operator, after) = rest.split_at(1);
+        if operator == "^"
+            && let Some(inner) = after.strip_prefix('{')
+        {
+            let close = inner.find('}').ok_or_else(|| invalid(spec))?;
+            hash = match &inner[..close] {
+                "" => peel_tags(repo_path, &hash)?,
+                kind => peel(repo_path, &hash, kind)?,
+            };
+            rest = &inner[close + 1..];
+            continue;
+        }
+
+        let digits = after.chars().take_while(char::is_ascii_digit).count();
+        let count: usize = match digits {
+            0 => 1,
+            _ => after[..digits].parse().map_err(|_| invalid(spec))?,
+        };
+        rest = &after[digits..];
+        match operator {
+            // ~n walks n first parents back
+            "~" => {
+                for _ in 0..count {
+                    hash = nth_parent(repo_path, &hash, 1, spec)?;
+                }
+            }
+            // ^n picks the n-th parent; ^0 is the commit itself
+            "^" if count == 0 => hash = peel(repo_path, &hash, "commit")?,
+            "^" => hash = nth_parent(repo_path, &hash, count, spec)?,
+            _ => return Err(invalid(spec)),
+        }
+    }
+    Ok(hash)
+}
+
+/// Resolves a revision that must name a commit, peeling tags on the way.
+pub(crate) fn resolve_commit(repo_path: &Path, spec: &str) -> io::Result<String> {
+    let hash = resolve_revision(repo_path, spec)?;
+    peel(repo_path, &hash, "commit").map_err(|_| io::Error::other(format!("'{}' is not a commit", spec)))
+}
+
+/// Splits `<rev>:<path>`, ignoring colons inside `@{...}`.
+fn split_path(spec: &str) -> Option<(&str, &str)> {
+    let mut depth = 0;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            ':' if depth == 0 => return Some((&spec[..i], &spec[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Where the `~`/`^` suffixes start, skipping over `@{...}` selectors.
+fn suffix_start(spec: &str) -> usize {
+    let mut depth = 0;
+    for (i, c) in spec.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            '~' | '^' if depth == 0 => return i,
+            _ => {}
+        }
+    }
+    spec.len()
+}
+
+fn invalid(spec: &str) -> io::Error {
+    io::Error::other(format!("invalid revision '{}'", spec))
+}
+
+/// Resolves the part of a revision before any `~`/`^` suffix.
+fn resolve_base(repo_path: &Path, base: &str) -> io::Result<String> {
+    if base.is_empty() || base == "@" {
+        return resolve_name(repo_path, "HEAD");
+    }
+
+    if let Some(open) = base.find("@{")
+        && let Some(selector) = base[open + 2..].strip_suffix('}')
+    {
+        // @{-n}: the branch we were on n checkouts ago
+        if let Some(n) = selector.strip_prefix('-') {
+            let n: usize = n.parse().map_err(|_| invalid(base))?;
+            let branch = previous_branch(repo_path, n)?;
+            return resolve_name(repo_path, &branch);
+        }
+        let refname = match &base[..open] {
+            "" => current_ref(),
+            "HEAD" => "HEAD".to_string(),
+            name => full_ref_name(repo_path, name).ok_or_else(|| unknown(name))?,
+        };
+        // @{n} counts reflog entries, anything else is a date like @{yesterday}
+        if let Ok(n) = selector.parse::<usize>() {
+            return reflog_entry(repo_path, &refname, n);
+        }
+        let when = parse_date(selector).map_err(|_| invalid(base))?;
+        return reflog_at(repo_path, &refname, when);
+    }
+
+    resolve_name(repo_path, base)
+}
+
+/// Resolves a plain name: a special ref like `HEAD`, a full ref, a tag, a
+/// branch or an abbreviated object hash.
+fn resolve_name(repo_path: &Path, name: &str) -> io::Result<String> {
+    if name == "HEAD" {
+        return read_head_commit(repo_path)?.ok_or_else(|| io::Error::other("HEAD does not point to a commit yet"));
+    }
+    if let Some(refname) = full_ref_name(repo_path, name) {
+        return read_ref(repo_path, &refname)?.ok_or_else(|| unknown(name));
+    }
+    resolve_abbreviated(repo_path, name)
+}
+
+/// The full ref a short name refers to, in Git's order of precedence.
+fn full_ref_name(repo_path: &Path, name: &str) -> Option<String> {
+    let candidates = [
+        is_special_ref(name).then(|| name.to_string()),
+        Some(name.to_string()).filter(|n| n.starts_with("refs/")),
+        Some(format!("refs/tags/{}", name)),
+        Some(format!("refs/heads/{}", name)),
+    ];
+    candidates
+        .into_iter()
+        .flatten()
+        .find(|candidate| match is_special_ref(candidate) {
+            true => repo_path.join(candidate).is_file(),
+            false => check_ref_format(candidate).is_ok() && ref_exists(repo_path, candidate).unwrap_or(false),
+        })
+}
+
+/// Looks up an object by a unique prefix of its hash.
+fn resolve_abbreviated(repo_path: &Path, name: &str) -> io::Result<String> {
+    if name.len() < 4 || name.len() > 40 || !name.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(unknown(name));
+    }
+    let name = name.to_ascii_lowercase();
+    let (dir_name, rest) = name.split_at(2);
+    let mut matches = Vec::new();
+    let obj_dir = repo_path.join("objects").join(dir_name);
+    if obj_dir.is_dir() {
+        for entry in fs::read_dir(obj_dir)? {
+            let file_name = entry?.file_name().to_string_lossy().into_owned();
+            if file_name.starts_with(rest) {
+                matches.push(format!("{}{}", dir_name, file_name));
+            }
+        }
+    }
+    matches.sort();
+
+    match matches.len() {
+        0 => Err(unknown(&name)),
+        1 => Ok(matches.remove(0)),
+        _ => {
+            let mut message = format!("short object ID {} is ambiguous\nhint: The candidates are:", name);
+            for hash in &matches {
+                let kind = object_type(repo_path, hash).unwrap_or_default();
+                message.push_str(&format!("\nhint:   {} {}", &hash[..name.len().max(7)], kind));
+            }
+            Err(io::Error::other(message))
+        }
+    }
+}
+
+fn unknown(name: &str) -> io::Error {
+    io::Error::other(format!("unknown revision '{}'", name))
+}
+
+/// The n-th parent of a commit, counting from 1.
+fn nth_parent(repo_path: &Path, hash: &str, n: usize, spec: &str) -> io::Result<String> {
+    let commit = peel(repo_path, hash, "commit")?;
+    read_commit(repo_path, &commit)?
+        .parents
+        .get(n - 1)
+        .cloned()
+        .ok_or_else(|| io::Error::other(format!("revision '{}' does not exist: the commit has too few parents", spec)))
+}
+
+/// Follows annotated tags until reaching a non-tag object.
+pub(crate) fn peel_tags(repo_path: &Path, hash: &str) -> io::Result<String> {
+    let mut hash = hash.to_string();
+    while object_type(repo_path, &hash)? == "tag" {
+        let content = read_object(repo_path, &hash)?;
+        hash = String::from_utf8_lossy(&content)
+            .lines()
+            .find_map(|line| line.strip_prefix("object ").map(str::to_string))
+            .ok_or_else(|| io::Error::other(format!("tag {} has no object", hash)))?;
+    }
+    Ok(hash)
+}
+
+/// Peels an object until it has the wanted type: tags to what they point at
+/// and commits to their tree.
+pub(crate) fn peel(repo_path: &Path, hash: &str, kind: &str) -> io::Result<String> {
+    let mut hash = peel_tags(repo_path, hash)?;
+    let mut current = object_type(repo_path, &hash)?;
+    if current == "commit" && kind == "tree" {
+        hash = read_commit(repo_path, &hash)?.tree;
+        current = "tree".to_string();
+    }
+    if current != kind {
+        return Err(io::Error::other(format!("{} is a {}, not a {}", &hash[..7], current, kind)));
+    }
+    Ok(hash)
+}
@@ -0,0 +1,108 @@
+use super::commit::read_commit;
+use super::diff::{detect_renames, print_changes, print_combined_diff, DiffOptions, DEFAULT_SIMILARITY};
+use super::log::{commit_changes, decorations, format_commit, format_date, CommitFormat};
+use super::tag::read_tag;
+use colored::Colorize;
+use super::revision::resolve_revision;
+use super::utils::{find_repo_root, get_tree_hash, object_type, read_object, read_tree, read_tree_entries};
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub fn run(revision: &str, format: &CommitFormat, stat: bool) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    let hash = resolve_revision(&repo_path, revision)?;
+    match object_type(&repo_path, &hash)?.as_str() {
+        "tree" => show_tree(&repo_path, revision, &hash),
+        "blob" => show_blob(&repo_path, &hash),
+        "tag" => show_tag(&repo_path, &hash, format, stat),
+        _ => show_commit(&repo_path, &hash, format, stat),
+    }
+}
+
+/// Prints an annotated tag's header and message, then the object it tags.
+fn show_tag(repo_path: &Path, hash: &str, format: &CommitFormat, stat: bool) -> io::Result<()> {
+    let tag = read_tag(repo_path, hash)?;
+    println!("{}", format!("tag {}", tag.name).yellow());
+    if let Some(tagger) = &tag.tagger {
+        println!("Tagger: {} <{}>", tagger.name, tagger.email);
+        println!("Date:   {}", format_date(tagger, format.date));
+    }
+    println!("\n{}\n", tag.message.trim_end());
+
+    match object_type(repo_path, &tag.object)?.as_str() {
+        "tree" => show_tree(repo_path, &tag.object, &tag.object),
+        "blob" => show_blob(repo_path, &tag.object),
+        "tag" => show_tag(repo_path, &tag.object, format, stat),
+        _ => show_commit(repo_path, &tag.object, format, stat),
+    }
+}
+
+/// Prints a commit's header followed by its changes: a regular diff against
+/// its parent, or a combined diff for merges.
+fn show_commit(repo_path: &Path, hash: &str, format: &CommitFormat, stat: bool) -> io::Result<()> {
+    let commit = read_commit(repo_path, hash)?;
+    for line in format_commit(hash, &commit, decorations(repo_path)?.get(hash), format) {
+        println!("{}", line);
+    }
+    let separator = !format.pretty.is_single_line();
+
+    // Like Git, --stat summarises a merge against its first parent
+    if commit.parents.len() > 1 && !stat {
+        let result = read_tree_entries(repo_path, &commit.tree)?;
+        let parents = commit
+            .parents
+            .iter()
+            .map(|parent| read_tree_entries(repo_path, &get_tree_hash(repo_path, parent)?))
+            .collect::<io::Result<Vec<_>>>()?;
+
+        // Only files that differ from every parent were touched by the merge itself
+        let paths: BTreeSet<_> = result.keys().chain(parents.iter().flat_map(|p| p.keys())).collect();
+        let mut first = true;
+        for path in paths {
+            let own = result.get(path).map(|(_, hash)| hash);
+            if parents.iter().any(|p| p.get(path).map(|(_, hash)| hash) == own) {
+                continue;
+            }
+            if first && separator {
+                println!();
+            }
+            first = false;
+
+            let blob = |hash: Option<&String>| hash.map(|h| read_object(repo_path, h)).transpose();
+            let sides = parents
+                .iter()
+                .map(|p| blob(p.get(path).map(|(_, hash)| hash)))
+                .collect::<io::Result<Vec<_>>>()?;
+            print_combined_diff(path, &sides, blob(own)?.as_deref());
+        }
+        return Ok(());
+    }
+
+    let changes = commit_changes(repo_path, &commit.tree, commit.parents.first().map(String::as_str))?;
+    let changes = detect_renames(changes, Some(DEFAULT_SIMILARITY), None);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    if separator {
+        println!();
+    }
+    print_changes(repo_path, &changes, &DiffOptions { stat, ..Default::default() })
+}
+
+/// Lists a tree's entries the way `git show` does, marking directories
+/// with a trailing slash.
+fn show_tree(repo_path: &Path, name: &str, hash: &str) -> io::Result<()> {
+    println!("tree {}\n", name);
+    for (mode, entry, _) in read_tree(repo_path, hash)? {
+        match mode.as_str() {
+            "40000" => println!("{}/", entry),
+            _ => println!("{}", entry),
+        }
+    }
+    Ok(())
+}
+
+fn show_blob(repo_path: &Path, hash: &str) -> io::Result<()> {
+    io::stdout().write_all(&read_object(repo_path, hash)?)
+}
@@ -1,20 +1,25 @@
-use super::utils::{find_repo_root, get_current_branch, load_ritignore, is_ignored};
+use super::utils::{
+    blob_object, find_repo_root, get_current_branch, is_ignored, load_ritignore, read_ref, read_worktree_file,
+    trust_file_mode, MODE_SYMLINK,
+};
 use super::commit::read_index;
-use sha1::{Digest, Sha1};
+use super::merge::{merge_head, read_conflicts};
+use super::diff::{changes_between, detect_renames, head_tree_map, ChangeKind, FileMap, DEFAULT_SIMILARITY};
 use std::collections::HashSet;
 use std::fs;
 use std::io;
 use std::path::{Path, PathBuf};
 
-pub fn run() -> io::Result<()> {
+/// Renames among staged changes are always detected; `find_renames` only
+/// changes the similarity threshold, while `find_copies` enables copy detection.
+pub fn run(find_renames: Option<u8>, find_copies: Option<u8>) -> io::Result<()> {
     let repo_root = find_repo_root()?;
     let work_dir = repo_root.parent().unwrap().to_path_buf();
     
     // 1. Print current branch or detached HEAD
     let has_commits = match get_current_branch()? {
         Some(branch) => {
-            let head_ref = repo_root.join("refs").join("heads").join(&branch);
-            if head_ref.exists() && !fs::read_to_string(&head_ref)?.trim().is_empty() {
+            if read_ref(&repo_root, &format!("refs/heads/{}", branch))?.is_some() {
                 println!("On branch {}", branch);
                 true
             } else {
@@ -74,46 +79,102 @@ pub fn run() -> io::Result<()> {
         return Ok(());
     }
     
-    // 4. Check for modified files (tracked but changed)
+    // 4. Compare the index with HEAD to find staged changes
+    let index_map: FileMap = index_entries
+        .iter()
+        .map(|entry| (entry.path.clone(), (entry.mode.clone(), entry.sha1.clone())))
+        .collect();
+    let staged_changes = detect_renames(
+        changes_between(&repo_root, &head_tree_map(&repo_root)?, &index_map)?,
+        Some(find_renames.unwrap_or(DEFAULT_SIMILARITY)),
+        find_copies,
+    );
+
+    // 5. Check for modified and deleted files (tracked but changed)
     let mut modified_files = Vec::new();
+    let mut deleted_files = Vec::new();
+    let check_mode = trust_file_mode(&repo_root)?;
     for entry in &index_entries {
         let normalized = entry.path.to_string_lossy();
         let normalized_path = normalized.trim_start_matches("./");
         let file_path = work_dir.join(normalized_path);
         
-        if file_path.exists() {
-            // Compute current hash
-            let contents = fs::read(&file_path)?;
-            let blob_header = format!("blob {}\0", contents.len());
-            let mut blob_data = Vec::new();
-            blob_data.extend_from_slice(blob_header.as_bytes());
-            blob_data.extend_from_slice(&contents);
-            
-            let mut hasher = Sha1::new();
-            hasher.update(&blob_data);
-            let current_hash = hex::encode(hasher.finalize());
-            
-            if current_hash != entry.sha1 {
-                modified_files.push(normalized_path.to_string());
+        match read_worktree_file(&file_path)? {
+            Some((mode, contents)) => {
+                // Compute current hash; the executable bit only counts with core.fileMode
+                let (current_hash, _) = blob_object(&contents);
+                let mode_changed = mode != entry.mode && (check_mode || mode == MODE_SYMLINK || entry.mode == MODE_SYMLINK);
+                if current_hash != entry.sha1 || mode_changed {
+                    modified_files.push(normalized_path.to_string());
+                }
             }
+            None => deleted_files.push(normalized_path.to_string()),
         }
     }
     
-    // 5. Find untracked files
+    // 6. Find untracked files
     let ignore_patterns = load_ritignore(&repo_root)?;
     let mut untracked_files = Vec::new();
     collect_untracked(&work_dir, &work_dir, &repo_root, &tracked_files, &ignore_patterns, &mut untracked_files)?;
     
-    // 6. Display results
+    // 7. Display results
     println!();
     
-    if !modified_files.is_empty() {
+    // Conflicted files are listed as unmerged rather than modified
+    let conflicts = read_conflicts(&repo_root)?;
+    modified_files.retain(|file| !conflicts.iter().any(|c| c.path == Path::new(file)));
+    if merge_head(&repo_root)?.is_some() {
+        if conflicts.is_empty() {
+            println!("All conflicts fixed but you are still merging.");
+            println!("  (use \"cargo run commit\" to conclude merge)");
+            println!();
+        } else {
+            println!("You have unmerged paths.");
+            println!("  (fix conflicts and run \"cargo run commit\")");
+            println!();
+            println!("Unmerged paths:");
+            println!("  (use \"cargo run add <file>...\" or \"cargo run mergetool\" to mark resolution)");
+            println!();
+            for conflict in &conflicts {
+                let state = match (&conflict.ours, &conflict.theirs) {
+                    (Some(_), None) => "deleted by them:",
+                    (None, Some(_)) => "deleted by us:",
+                    _ if conflict.base.is_none() => "both added:",
+                    _ => "both modified:",
+                };
+                println!("  {:<17}{}", state, conflict.path.display());
+            }
+            println!();
+        }
+    }
+    
+    if !staged_changes.is_empty() {
+        println!("Changes to be committed:");
+        println!("  (use \"cargo run rm --cached <file>...\" to unstage)");
+        println!();
+        for change in &staged_changes {
+            let from = change.old_path.as_ref().map(|p| p.display().to_string()).unwrap_or_default();
+            match change.kind {
+                ChangeKind::Added => println!("  new file:   {}", change.path.display()),
+                ChangeKind::Modified => println!("  modified:   {}", change.path.display()),
+                ChangeKind::Deleted => println!("  deleted:    {}", change.path.display()),
+                ChangeKind::Renamed(_) => println!("  renamed:    {} -> {}", from, change.path.display()),
+                ChangeKind::Copied(_) => println!("  copied:     {} -> {}", from, change.path.display()),
+            }
+        }
+        println!();
+    }
+    
+    if !modified_files.is_empty() || !deleted_files.is_empty() {
         println!("Changes not staged for commit:");
         println!("  (use \"cargo run add <file>...\" to update what will be committed)");
         println!();
         for file in &modified_files {
             println!("  modified:   {}", file);
         }
+        for file in &deleted_files {
+            println!("  deleted:    {}", file);
+        }
         println!();
     }
     
@@ -127,7 +188,7 @@ pub fn run() -> io::Result<()> {
         println!();
     }
     
-    if modified_files.is_empty() && untracked_files.is_empty() {
+    if staged_changes.is_empty() && modified_files.is_empty() && deleted_files.is_empty() && untracked_files.is_empty() {
         println!("nothing to commit, working tree clean");
     }
     
@@ -163,9 +224,11 @@ fn collect_untracked(
             continue;
         }
         
-        if entry.is_dir() {
+        // Symlinks are listed like files, never followed into
+        let file_type = fs::symlink_metadata(&entry)?.file_type();
+        if file_type.is_dir() {
             collect_untracked(&entry, work_dir, repo_root, tracked_files, ignores, untracked)?;
-        } else if entry.is_file() {
+        } else if file_type.is_file() || file_type.is_symlink() {
             let rel_path = entry.strip_prefix(work_dir).unwrap();
             
             // Check if this file is tracked
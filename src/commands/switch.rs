@@ -0,0 +1,41 @@
+use super::checkout::{create_and_switch, detach_at, switch_branch};
+use super::reflog::previous_branch;
+use super::utils::{check_short_ref_name, find_repo_root, ref_exists};
+use std::io;
+
+/// Options for `rit switch`.
+#[derive(Debug, Default)]
+pub struct SwitchOptions {
+    /// `-c`/`-C`: create this branch at the target and switch to it
+    pub create: Option<String>,
+    /// `-C`: reset the branch if it already exists
+    pub force_create: bool,
+    /// Detach HEAD at the target instead of switching to a branch
+    pub detach: bool,
+}
+
+/// Switches to a branch. Unlike checkout, a plain switch only accepts
+/// branch names; detaching HEAD has to be asked for with `--detach`.
+pub fn run(target: Option<&str>, options: &SwitchOptions) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    if let Some(name) = &options.create {
+        return create_and_switch(&repo_path, name, target, options.force_create);
+    }
+    if options.detach {
+        return detach_at(&repo_path, target.unwrap_or("HEAD"));
+    }
+
+    let target = match target {
+        Some("-") => previous_branch(&repo_path, 1)?,
+        Some(target) => target.to_string(),
+        None => return Err(io::Error::other("missing branch to switch to")),
+    };
+    check_short_ref_name(&target, "branch")?;
+    if !ref_exists(&repo_path, &format!("refs/heads/{}", target))? {
+        return Err(io::Error::other(format!(
+            "a branch is expected, got '{}'\nhint: use --detach to check out a commit, or -c to create a new branch",
+            target
+        )));
+    }
+    switch_branch(&repo_path, &target)
+}
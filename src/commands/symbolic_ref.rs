@@ -0,0 +1,57 @@
+use super::reflog::append_reflog;
+use super::utils::{
+    check_ref_name, delete_symbolic_ref, find_repo_root, read_ref, read_symbolic_ref, resolve_symbolic_ref,
+    write_symbolic_ref,
+};
+use std::io;
+
+/// Options for `rit symbolic-ref`.
+#[derive(Debug, Default)]
+pub struct SymbolicRefOptions {
+    /// Print `main` instead of `refs/heads/main`
+    pub short: bool,
+    /// Fail silently (exit status 1) if the ref is not symbolic
+    pub quiet: bool,
+    pub delete: bool,
+    /// Reflog message for a change of target
+    pub message: Option<String>,
+}
+
+/// Reads, changes or deletes a symbolic ref such as HEAD.
+pub fn run(name: &str, target: Option<&str>, options: &SymbolicRefOptions) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    check_ref_name(name)?;
+
+    if options.delete {
+        if name == "HEAD" {
+            return Err(io::Error::other("deleting 'HEAD' is not allowed"));
+        }
+        if read_symbolic_ref(&repo_path, name)?.is_none() {
+            return Err(io::Error::other(format!("Cannot delete {}, not a symbolic ref", name)));
+        }
+        return delete_symbolic_ref(&repo_path, name);
+    }
+
+    if let Some(target) = target {
+        let old = read_ref(&repo_path, name).ok().flatten().unwrap_or_default();
+        write_symbolic_ref(&repo_path, name, target)?;
+        if let Some(message) = &options.message {
+            let new = read_ref(&repo_path, name)?.unwrap_or_default();
+            append_reflog(&repo_path, name, &old, &new, message)?;
+        }
+        return Ok(());
+    }
+
+    if read_symbolic_ref(&repo_path, name)?.is_none() {
+        if options.quiet {
+            std::process::exit(1);
+        }
+        return Err(io::Error::other(format!("ref {} is not a symbolic ref", name)));
+    }
+    let resolved = resolve_symbolic_ref(&repo_path, name)?;
+    match options.short {
+        true => println!("{}", resolved.trim_start_matches("refs/heads/").trim_start_matches("refs/tags/")),
+        false => println!("{}", resolved),
+    }
+    Ok(())
+}
@@ -0,0 +1,147 @@
+use super::commit::{identity, Signature};
+use super::revision::{peel, resolve_revision};
+use super::utils::{check_short_ref_name, delete_ref, find_repo_root, list_refs, object_type, read_object, read_ref, ref_exists, update_ref, write_object};
+use glob::Pattern;
+use sha1::{Digest, Sha1};
+use std::io;
+use std::path::Path;
+
+/// Options for `rit tag`.
+#[derive(Debug, Default)]
+pub struct TagOptions {
+    /// The tag to create or delete, or the pattern to list with `-l`
+    pub name: Option<String>,
+    /// What the new tag points at; defaults to HEAD
+    pub target: Option<String>,
+    /// Create an annotated tag object instead of a plain ref
+    pub annotate: bool,
+    pub message: Option<String>,
+    pub delete: bool,
+    pub list: bool,
+    /// Replace an existing tag
+    pub force: bool,
+}
+
+/// A parsed annotated tag object.
+#[derive(Debug, Clone)]
+pub(crate) struct Tag {
+    pub object: String,
+    pub kind: String,
+    pub name: String,
+    pub tagger: Option<Signature>,
+    pub message: String,
+}
+
+impl Tag {
+    pub fn parse(data: &[u8]) -> Tag {
+        let content = String::from_utf8_lossy(data);
+        let (headers, message) = content.split_once("\n\n").unwrap_or((&content, ""));
+        let mut tag = Tag {
+            object: String::new(),
+            kind: String::new(),
+            name: String::new(),
+            tagger: None,
+            message: message.to_string(),
+        };
+        for line in headers.lines() {
+            if let Some(object) = line.strip_prefix("object ") {
+                tag.object = object.to_string();
+            } else if let Some(kind) = line.strip_prefix("type ") {
+                tag.kind = kind.to_string();
+            } else if let Some(name) = line.strip_prefix("tag ") {
+                tag.name = name.to_string();
+            } else if let Some(tagger) = line.strip_prefix("tagger ") {
+                tag.tagger = Some(Signature::parse(tagger));
+            }
+        }
+        tag
+    }
+}
+
+/// Reads and parses an annotated tag object.
+pub(crate) fn read_tag(repo_path: &Path, hash: &str) -> io::Result<Tag> {
+    Ok(Tag::parse(&read_object(repo_path, hash)?))
+}
+
+pub fn run(options: &TagOptions) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+
+    match (&options.name, options.delete) {
+        (Some(name), true) => delete_tag(&repo_path, name),
+        (None, true) => Err(io::Error::other("tag name required")),
+        (Some(name), false) if !options.list => create_tag(&repo_path, name, options),
+        (pattern, false) => list_tags(&repo_path, pattern.as_deref()),
+    }
+}
+
+fn create_tag(repo_path: &Path, name: &str, options: &TagOptions) -> io::Result<()> {
+    check_short_ref_name(name, "tag")?;
+    let refname = format!("refs/tags/{}", name);
+    let existing = ref_exists(repo_path, &refname)?;
+    if existing && !options.force {
+        return Err(io::Error::other(format!("tag '{}' already exists", name)));
+    }
+    let target = resolve_revision(repo_path, options.target.as_deref().unwrap_or("HEAD"))?;
+    let reason = format!("tag: tagging {}", &target[..7]);
+
+    // A message always makes the tag annotated, as in Git
+    let hash = match (options.annotate, &options.message) {
+        (false, None) => target,
+        (_, None) => return Err(io::Error::other("annotated tags need a message (-m)")),
+        (_, Some(message)) => {
+            let tagger = identity(repo_path, "committer")?;
+            let mut message = message.trim_end().to_string();
+            message.push('\n');
+            let content = format!(
+                "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+                target,
+                object_type(repo_path, &target)?,
+                name,
+                tagger,
+                message
+            );
+
+            let mut tag_object = format!("tag {}\0", content.len()).into_bytes();
+            tag_object.extend_from_slice(content.as_bytes());
+            let mut hasher = Sha1::new();
+            hasher.update(&tag_object);
+            let hash = hex::encode(hasher.finalize());
+            write_object(repo_path, &hash, &tag_object)?;
+            hash
+        }
+    };
+
+    update_ref(repo_path, &refname, &hash, None, &reason)?;
+    if existing {
+        println!("Updated tag '{}'", name);
+    }
+    Ok(())
+}
+
+fn delete_tag(repo_path: &Path, name: &str) -> io::Result<()> {
+    check_short_ref_name(name, "tag")?;
+    let refname = format!("refs/tags/{}", name);
+    let Some(hash) = read_ref(repo_path, &refname)? else {
+        return Err(io::Error::other(format!("tag '{}' not found.", name)));
+    };
+    delete_ref(repo_path, &refname, Some(&hash))?;
+    println!("Deleted tag '{}' (was {})", name, &hash[..7]);
+    Ok(())
+}
+
+fn list_tags(repo_path: &Path, pattern: Option<&str>) -> io::Result<()> {
+    let pattern = pattern
+        .map(|p| Pattern::new(p).map_err(|e| io::Error::other(format!("invalid pattern '{}': {}", p, e))))
+        .transpose()?;
+    for (name, _) in list_refs(repo_path, "tags")? {
+        if pattern.as_ref().is_none_or(|p| p.matches(&name)) {
+            println!("{}", name);
+        }
+    }
+    Ok(())
+}
+
+/// The commit a tag ultimately points at, if it points at a commit at all.
+pub(crate) fn tagged_commit(repo_path: &Path, hash: &str) -> Option<String> {
+    peel(repo_path, hash, "commit").ok()
+}
@@ -0,0 +1,26 @@
+use super::revision::resolve_revision;
+use super::utils::{delete_ref, find_repo_root};
+use std::io;
+
+/// Sets (or with `delete`, removes) a ref. When `old` is given the ref is
+/// only changed if it still holds that value; an empty `old` or the all-zero
+/// hash requires the ref not to exist yet. Updates are logged with `message`.
+pub fn run(name: &str, new: Option<&str>, old: Option<&str>, delete: bool, message: &str) -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    if !delete && new.is_none() {
+        return Err(io::Error::other("update-ref needs a new value, or -d to delete"));
+    }
+
+    let expected = old
+        .map(|old| match old.trim_start_matches('0') {
+            "" => Ok(String::new()),
+            _ => resolve_revision(&repo_path, old),
+        })
+        .transpose()?;
+
+    if delete {
+        return delete_ref(&repo_path, name, expected.as_deref());
+    }
+    let new_hash = resolve_revision(&repo_path, new.unwrap())?;
+    super::utils::update_ref(&repo_path, name, &new_hash, expected.as_deref(), message)
+}
@@ -1,8 +1,11 @@
 use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
 use std::fs;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, BufRead, Read, Write};
 use std::path::{Path, PathBuf};
 use glob::Pattern;
+use sha1::{Digest, Sha1};
+use super::reflog::{append_reflog, delete_reflog};
 
 /// Walk upward to find `.rit` repo root
 pub fn find_repo_root() -> io::Result<PathBuf> {
@@ -34,55 +37,575 @@ pub fn write_object(repo_path: &Path, hash: &str, data: &[u8]) -> io::Result<()>
     Ok(())
 }
 
+/// Build the raw `blob <size>\0<contents>` object for some file contents
+/// and return it together with its hash.
+pub fn blob_object(contents: &[u8]) -> (String, Vec<u8>) {
+    let mut blob_data = format!("blob {}\0", contents.len()).into_bytes();
+    blob_data.extend_from_slice(contents);
 
-/// Get the current commit hash from HEAD (if any)
-pub fn read_head_commit(repo_path: &Path) -> io::Result<Option<String>> {
-    let head_path = repo_path.join("HEAD");
-    if !head_path.exists() {
+    let mut hasher = Sha1::new();
+    hasher.update(&blob_data);
+    (hex::encode(hasher.finalize()), blob_data)
+}
+
+/// Index and tree modes for the kinds of file a working tree can hold.
+pub const MODE_FILE: &str = "100644";
+pub const MODE_EXECUTABLE: &str = "100755";
+pub const MODE_SYMLINK: &str = "120000";
+
+/// Reads a working tree path the way it is stored in a blob: its mode and
+/// contents, where a symlink's contents are its target rather than the file
+/// it points to. Returns `None` if there is no file or symlink at `path`.
+pub fn read_worktree_file(path: &Path) -> io::Result<Option<(String, Vec<u8>)>> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    if metadata.file_type().is_symlink() {
+        let target = fs::read_link(path)?;
+        return Ok(Some((MODE_SYMLINK.to_string(), target.to_string_lossy().into_owned().into_bytes())));
+    }
+    if !metadata.is_file() {
         return Ok(None);
     }
-    let content = fs::read_to_string(head_path)?;
-    if let Some(stripped) = content.strip_prefix("ref: ") {
-        let branch_path = repo_path.join(stripped.trim());
-        if branch_path.exists() {
-            let hash = fs::read_to_string(branch_path)?;
-            return Ok(Some(hash.trim().to_string()));
+
+    #[cfg(unix)]
+    let executable = {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o111 != 0
+    };
+    #[cfg(not(unix))]
+    let executable = false;
+
+    let mode = if executable { MODE_EXECUTABLE } else { MODE_FILE };
+    Ok(Some((mode.to_string(), fs::read(path)?)))
+}
+
+/// Writes a blob to the working tree as `mode`: a symlink to `contents` for
+/// 120000, otherwise a file, executable for 100755. Whatever is at `path`
+/// is replaced. Without symlink support the target is written as a file.
+pub fn write_worktree_file(path: &Path, mode: &str, contents: &[u8]) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if fs::symlink_metadata(path).is_ok_and(|metadata| !metadata.is_dir()) {
+        fs::remove_file(path)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if mode == MODE_SYMLINK {
+            return std::os::unix::fs::symlink(String::from_utf8_lossy(contents).as_ref(), path);
+        }
+        fs::write(path, contents)?;
+        let permissions = if mode == MODE_EXECUTABLE { 0o755 } else { 0o644 };
+        fs::set_permissions(path, fs::Permissions::from_mode(permissions))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = mode;
+        fs::write(path, contents)
+    }
+}
+
+/// Removes a file or symlink from the working tree, along with any
+/// directories that leaves empty (but never `work_dir` itself).
+pub fn remove_worktree_file(work_dir: &Path, path: &Path) -> io::Result<()> {
+    let file = work_dir.join(path);
+    if fs::symlink_metadata(&file).is_ok_and(|metadata| !metadata.is_dir()) {
+        fs::remove_file(&file)?;
+    }
+    for dir in file.ancestors().skip(1) {
+        if dir == work_dir || fs::remove_dir(dir).is_err() {
+            break;
         }
-    } else {
-        // detached HEAD
-        return Ok(Some(content.trim().to_string()));
     }
-    Ok(None)
+    Ok(())
+}
+
+/// Whether the executable bit in the working tree can be trusted
+/// (`core.fileMode`, true unless set to false). When it can't, mode
+/// changes are ignored and staged files keep the mode they had.
+pub fn trust_file_mode(repo_path: &Path) -> io::Result<bool> {
+    // Config keys are case-insensitive, so `core.filemode` counts too
+    let config = super::config::read_config_file(repo_path)?;
+    let value = config.iter().find(|(key, _)| key.eq_ignore_ascii_case("core.fileMode")).map(|(_, value)| value);
+    Ok(!matches!(value.map(|v| v.to_ascii_lowercase()).as_deref(), Some("false" | "no" | "off" | "0")))
+}
+
+/// Heuristically decide whether some content is binary rather than text.
+///
+/// Like Git, only the first 8000 bytes are inspected: any NUL byte marks the
+/// content as binary, as does a high proportion of non-printable control bytes.
+pub fn is_binary(data: &[u8]) -> bool {
+    let sample = &data[..data.len().min(8000)];
+    if sample.contains(&0) {
+        return true;
+    }
+    let control = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\t' | b'\n' | b'\r' | 0x0c | 0x1b | 0x08))
+        .count();
+    control * 10 > sample.len()
 }
 
+/// Get the current commit hash from HEAD (if any)
+pub fn read_head_commit(repo_path: &Path) -> io::Result<Option<String>> {
+    if !repo_path.join("HEAD").exists() {
+        return Ok(None);
+    }
+    read_ref(repo_path, "HEAD")
+}
+
+/// The branch HEAD is on, or `None` when HEAD is detached.
 pub fn get_current_branch() -> io::Result<Option<String>> {
     let repo_root = find_repo_root()?;
-    let head_path = repo_root.join("HEAD");
+    if read_symbolic_ref(&repo_root, "HEAD")?.is_none() {
+        return Ok(None);
+    }
+    let target = resolve_symbolic_ref(&repo_root, "HEAD")?;
+    Ok(Some(target.strip_prefix("refs/heads/").unwrap_or(&target).to_string()))
+}
 
-    let head_content = fs::read_to_string(head_path)?;
+/// How many symbolic refs may be chained before resolving gives up.
+const MAX_SYMREF_DEPTH: usize = 5;
 
-    if head_content.starts_with("ref: ") {
-        let branch = head_content
-            .trim_start_matches("ref: refs/heads/")
-            .trim()
-            .to_string();
-        Ok(Some(branch))
-    } else {
-        Ok(None)
+/// The target of a symbolic ref (a loose ref file holding `ref: <target>`),
+/// or `None` if `name` is not symbolic.
+pub fn read_symbolic_ref(repo_path: &Path, name: &str) -> io::Result<Option<String>> {
+    // A malformed name could point anywhere, so it is never a symbolic ref
+    if check_ref_name(name).is_err() {
+        return Ok(None);
+    }
+    let path = repo_path.join(name);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(path)?;
+    Ok(content.strip_prefix("ref: ").map(|target| target.trim().to_string()))
+}
+
+/// Follows a chain of symbolic refs to the ref that actually holds a hash,
+/// e.g. `HEAD` to `refs/heads/main`. A name that is not symbolic resolves
+/// to itself.
+pub fn resolve_symbolic_ref(repo_path: &Path, name: &str) -> io::Result<String> {
+    let mut current = name.to_string();
+    let mut seen = vec![current.clone()];
+    while let Some(target) = read_symbolic_ref(repo_path, &current)? {
+        if seen.contains(&target) {
+            return Err(io::Error::other(format!(
+                "symbolic ref cycle: {} -> {}",
+                seen.join(" -> "),
+                target
+            )));
+        }
+        if seen.len() > MAX_SYMREF_DEPTH {
+            return Err(io::Error::other(format!(
+                "symbolic ref '{}' is nested more than {} levels deep",
+                name, MAX_SYMREF_DEPTH
+            )));
+        }
+        seen.push(target.clone());
+        current = target;
+    }
+    Ok(current)
+}
+
+/// Points the symbolic ref `name` at `target`, e.g. HEAD at a branch.
+pub fn write_symbolic_ref(repo_path: &Path, name: &str, target: &str) -> io::Result<()> {
+    if !target.starts_with("refs/") || check_ref_format(target).is_err() {
+        return Err(io::Error::other(format!("refusing to point {} outside of refs/: '{}'", name, target)));
+    }
+    check_ref_name(name)?;
+    let path = repo_path.join(name);
+    check_ref_conflicts(repo_path, name, &path)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    write_locked(&path, format!("ref: {}", target).as_bytes())
+}
+
+/// Deletes the symbolic ref `name` itself (not what it points at), along
+/// with its reflog.
+pub fn delete_symbolic_ref(repo_path: &Path, name: &str) -> io::Result<()> {
+    check_ref_name(name)?;
+    let path = repo_path.join(name);
+    fs::remove_file(&path)?;
+    remove_empty_ref_dirs(repo_path, &path);
+    delete_reflog(repo_path, name)
+}
+
+/// Checks that `name` can be looked up or written as a ref: a special ref
+/// such as `HEAD` or a well-formed ref name, so it stays inside the
+/// repository.
+pub fn check_ref_name(name: &str) -> io::Result<()> {
+    if is_special_ref(name) {
+        return Ok(());
+    }
+    check_ref_format(name).map_err(|reason| io::Error::other(format!("invalid ref name '{}': {}", name, reason)))
+}
+
+/// List the refs below `refs/<kind>` (e.g. `heads` or `tags`) as
+/// `(name, hash)` pairs sorted by name. Names are relative to that
+/// directory, so nested refs come out as `feature/x`. Loose refs take
+/// precedence over packed ones of the same name.
+pub fn list_refs(repo_path: &Path, kind: &str) -> io::Result<Vec<(String, String)>> {
+    let prefix = format!("refs/{}/", kind);
+    let mut refs: BTreeMap<String, String> = BTreeMap::new();
+    for packed in read_packed_refs(repo_path)? {
+        if let Some(name) = packed.name.strip_prefix(&prefix) {
+            refs.insert(name.to_string(), packed.hash);
+        }
+    }
+    for (name, hash) in loose_refs(repo_path, &format!("refs/{}", kind))? {
+        refs.insert(name[prefix.len()..].to_string(), hash);
+    }
+    Ok(refs.into_iter().collect())
+}
+
+/// The refs stored as files below `dir` (e.g. `refs/heads`), as full ref
+/// names with their hashes, sorted by name.
+pub fn loose_refs(repo_path: &Path, dir: &str) -> io::Result<Vec<(String, String)>> {
+    fn collect(dir: &Path, prefix: &str, refs: &mut Vec<(String, String)>) -> io::Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+            if entry.file_type()?.is_dir() {
+                collect(&entry.path(), &name, refs)?;
+            } else if !name.ends_with(".lock") {
+                let hash = fs::read_to_string(entry.path())?.trim().to_string();
+                if !hash.is_empty() {
+                    refs.push((name, hash));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    let mut refs = Vec::new();
+    if repo_path.join(dir).is_dir() {
+        collect(&repo_path.join(dir), dir, &mut refs)?;
+    }
+    // Symbolic refs list with the hash of the ref they point at; broken
+    // ones (dangling, cyclic or too deep) are left out
+    for (name, hash) in refs.iter_mut() {
+        if hash.starts_with("ref: ") {
+            *hash = read_ref(repo_path, name).ok().flatten().unwrap_or_default();
+        }
+    }
+    refs.retain(|(_, hash)| !hash.is_empty());
+    refs.sort();
+    Ok(refs)
+}
+
+/// A line of `.rit/packed-refs`: a ref and its hash, plus what an
+/// annotated tag peels to (the `^` line that follows it).
+#[derive(Debug, Clone)]
+pub struct PackedRef {
+    pub name: String,
+    pub hash: String,
+    pub peeled: Option<String>,
+}
+
+/// Reads `.rit/packed-refs`, which holds refs that `rit pack-refs` moved
+/// out of their individual files.
+pub fn read_packed_refs(repo_path: &Path) -> io::Result<Vec<PackedRef>> {
+    let path = repo_path.join("packed-refs");
+    if !path.is_file() {
+        return Ok(Vec::new());
+    }
+    let mut refs: Vec<PackedRef> = Vec::new();
+    for line in fs::read_to_string(path)?.lines() {
+        if line.starts_with('#') || line.is_empty() {
+            continue;
+        }
+        if let Some(peeled) = line.strip_prefix('^') {
+            if let Some(last) = refs.last_mut() {
+                last.peeled = Some(peeled.to_string());
+            }
+        } else if let Some((hash, name)) = line.split_once(' ') {
+            refs.push(PackedRef { name: name.to_string(), hash: hash.to_string(), peeled: None });
+        }
+    }
+    Ok(refs)
+}
+
+/// Replaces `.rit/packed-refs`, writing through `packed-refs.lock`.
+pub fn write_packed_refs(repo_path: &Path, refs: &[PackedRef]) -> io::Result<()> {
+    let mut refs = refs.to_vec();
+    refs.sort_by(|a, b| a.name.cmp(&b.name));
+    let mut content = String::from("# pack-refs with: peeled fully-peeled sorted \n");
+    for packed in &refs {
+        content.push_str(&format!("{} {}\n", packed.hash, packed.name));
+        if let Some(peeled) = &packed.peeled {
+            content.push_str(&format!("^{}\n", peeled));
+        }
+    }
+
+    write_locked(&repo_path.join("packed-refs"), content.as_bytes())
+}
+
+/// The hash a full ref such as `refs/heads/main` holds, looking at its
+/// loose file first and then at packed-refs.
+pub fn read_ref(repo_path: &Path, refname: &str) -> io::Result<Option<String>> {
+    check_ref_name(refname)?;
+    let refname = resolve_symbolic_ref(repo_path, refname)?;
+    let path = repo_path.join(&refname);
+    if path.is_file() {
+        let hash = fs::read_to_string(path)?.trim().to_string();
+        return Ok(Some(hash).filter(|h| !h.is_empty()));
     }
+    Ok(read_packed_refs(repo_path)?.into_iter().find(|packed| packed.name == refname).map(|packed| packed.hash))
 }
 
-/// Update HEAD to point at new commit hash
-pub fn update_head(repo_path: &Path, commit_hash: &str) -> io::Result<()> {
-    let head_path = repo_path.join("HEAD");
-    let content = fs::read_to_string(&head_path)?;
-    if let Some(stripped) = content.strip_prefix("ref: ") {
-        let branch_path = repo_path.join(stripped.trim());
-        fs::write(branch_path, commit_hash)?;
+/// Whether a full ref such as `refs/tags/v1.0` exists, loose or packed.
+pub fn ref_exists(repo_path: &Path, refname: &str) -> io::Result<bool> {
+    Ok(read_ref(repo_path, refname)?.is_some())
+}
+
+/// Checks a ref name such as `refs/heads/feature/x` against Git's
+/// `check-ref-format` rules, returning the reason it is invalid.
+pub fn check_ref_format(name: &str) -> Result<(), &'static str> {
+    if name.is_empty() || name == "@" {
+        return Err("the name is empty or '@'");
+    }
+    if name.starts_with('/') || name.ends_with('/') || name.contains("//") {
+        return Err("it has an empty path component");
+    }
+    if name.ends_with('.') {
+        return Err("it ends with '.'");
+    }
+    if name.contains("..") {
+        return Err("it contains '..'");
+    }
+    if name.contains("@{") {
+        return Err("it contains '@{'");
+    }
+    if let Some(c) = name.chars().find(|c| c.is_ascii_control() || " ~^:?*[\\".contains(*c)) {
+        return Err(match c {
+            ' ' => "it contains a space",
+            c if c.is_ascii_control() => "it contains a control character",
+            _ => "it contains one of ~ ^ : ? * [ or \\",
+        });
+    }
+    for component in name.split('/') {
+        if component.starts_with('.') {
+            return Err("a path component starts with '.'");
+        }
+        if component.ends_with(".lock") {
+            return Err("a path component ends with '.lock'");
+        }
+    }
+    Ok(())
+}
+
+/// Checks that `name` can be used as a branch (`kind` = "branch") or tag name.
+pub fn check_short_ref_name(name: &str, kind: &str) -> io::Result<()> {
+    let reason = match check_ref_format(&format!("refs/heads/{}", name)) {
+        Err(reason) => reason,
+        Ok(()) if name.starts_with('-') => "it starts with '-'",
+        Ok(()) if name == "HEAD" => "'HEAD' is reserved",
+        Ok(()) => return Ok(()),
+    };
+    Err(io::Error::other(format!("'{}' is not a valid {} name: {}", name, kind, reason)))
+}
+
+/// Whether `name` is a special ref kept directly in the repository
+/// directory, like `HEAD` or `ORIG_HEAD`.
+pub fn is_special_ref(name: &str) -> bool {
+    !name.is_empty() && name.chars().all(|c| c.is_ascii_uppercase() || c == '_')
+}
+
+/// A `<path>.lock` file, held while `path` is being replaced so that
+/// concurrent writers fail instead of clobbering each other. The lock is
+/// removed again when dropped, unless `commit` moved it into place.
+struct LockFile {
+    path: PathBuf,
+    lock_path: PathBuf,
+    file: fs::File,
+    committed: bool,
+}
+
+impl LockFile {
+    fn acquire(path: &Path) -> io::Result<LockFile> {
+        let lock_path = PathBuf::from(format!("{}.lock", path.display()));
+        let file = fs::OpenOptions::new().write(true).create_new(true).open(&lock_path).map_err(|e| {
+            let hint = match e.kind() {
+                io::ErrorKind::AlreadyExists => ". Another rit process seems to be running",
+                _ => "",
+            };
+            io::Error::other(format!("Unable to create '{}': {}{}", lock_path.display(), e, hint))
+        })?;
+        Ok(LockFile { path: path.to_path_buf(), lock_path, file, committed: false })
+    }
+
+    /// Writes `contents` to the lock and renames it over `path`.
+    fn commit(mut self, contents: &[u8]) -> io::Result<()> {
+        self.file.write_all(contents)?;
+        fs::rename(&self.lock_path, &self.path)?;
+        self.committed = true;
+        Ok(())
+    }
+}
+
+impl Drop for LockFile {
+    fn drop(&mut self) {
+        if !self.committed {
+            let _ = fs::remove_file(&self.lock_path);
+        }
+    }
+}
+
+/// Replaces `path` with `contents` through `<path>.lock`, so readers never
+/// see a half-written file.
+pub fn write_locked(path: &Path, contents: &[u8]) -> io::Result<()> {
+    LockFile::acquire(path)?.commit(contents)
+}
+
+/// Updates or deletes a ref under a `<ref>.lock` file. With `expected`, the
+/// change only happens if the ref currently holds that value; an empty
+/// string means the ref must not exist yet. Updates are recorded in the
+/// reflog with `message`; deleting a ref deletes its reflog.
+fn change_ref(repo_path: &Path, name: &str, new: Option<&str>, expected: Option<&str>, message: &str) -> io::Result<()> {
+    if !is_special_ref(name) {
+        if !name.starts_with("refs/") {
+            return Err(io::Error::other(format!("invalid ref name '{}': it is not below refs/", name)));
+        }
+        check_ref_format(name).map_err(|reason| io::Error::other(format!("invalid ref name '{}': {}", name, reason)))?;
+    }
+    // Updating a symbolic ref such as an attached HEAD updates its target
+    let refname = resolve_symbolic_ref(repo_path, name)?;
+    let path = repo_path.join(&refname);
+    if new.is_some() {
+        check_ref_conflicts(repo_path, &refname, &path)?;
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let lock = LockFile::acquire(&path)?;
+
+    let mut current = String::new();
+    let result = (|| {
+        current = read_ref(repo_path, &refname)?.unwrap_or_default();
+        if let Some(expected) = expected
+            && current != expected
+        {
+            return Err(io::Error::other(format!(
+                "cannot lock ref '{}': is at {} but expected {}",
+                name,
+                if current.is_empty() { "nothing" } else { &current },
+                if expected.is_empty() { "nothing" } else { expected }
+            )));
+        }
+        match new {
+            Some(hash) => lock.commit(hash.as_bytes()),
+            None if current.is_empty() => Err(io::Error::other(format!("ref '{}' does not exist", name))),
+            None => {
+                // The ref may live in its own file, in packed-refs, or both
+                if path.is_file() {
+                    fs::remove_file(&path)?;
+                }
+                let mut packed = read_packed_refs(repo_path)?;
+                if packed.iter().any(|p| p.name == refname) {
+                    packed.retain(|p| p.name != refname);
+                    write_packed_refs(repo_path, &packed)?;
+                }
+                Ok(())
+            }
+        }
+    })();
+    result?;
+
+    match new {
+        Some(hash) => {
+            append_reflog(repo_path, &refname, &current, hash, message)?;
+            // Moving the checked-out branch moves HEAD too
+            if refname != "HEAD" && resolve_symbolic_ref(repo_path, "HEAD").ok().as_deref() == Some(refname.as_str()) {
+                append_reflog(repo_path, "HEAD", &current, hash, message)?;
+            }
+        }
+        None => {
+            remove_empty_ref_dirs(repo_path, &path);
+            delete_reflog(repo_path, &refname)?;
+        }
     }
     Ok(())
 }
 
+/// Refuses to create a ref whose file would clash with an existing ref
+/// directory (`feature` while `feature/x` exists) or whose directory would
+/// clash with an existing ref file (`feature/x` while `feature` exists).
+fn check_ref_conflicts(repo_path: &Path, refname: &str, path: &Path) -> io::Result<()> {
+    let display = |p: &Path| p.strip_prefix(repo_path).unwrap_or(p).display().to_string();
+    if path.is_dir() {
+        // A directory left without any refs in it is simply in the way
+        match first_file(path) {
+            Some(existing) => {
+                return Err(io::Error::other(format!(
+                    "cannot create '{}': there are refs below it, e.g. '{}'",
+                    display(path),
+                    display(&existing)
+                )));
+            }
+            None => fs::remove_dir_all(path)?,
+        }
+    }
+    let refs_dir = repo_path.join("refs");
+    for ancestor in path.ancestors().skip(1).take_while(|p| p.starts_with(&refs_dir)) {
+        if ancestor.is_file() {
+            return Err(io::Error::other(format!(
+                "cannot create '{}': '{}' exists",
+                display(path),
+                display(ancestor)
+            )));
+        }
+    }
+    for packed in read_packed_refs(repo_path)? {
+        if packed.name.starts_with(&format!("{}/", refname)) || refname.starts_with(&format!("{}/", packed.name)) {
+            return Err(io::Error::other(format!("cannot create '{}': '{}' exists", refname, packed.name)));
+        }
+    }
+    Ok(())
+}
+
+fn first_file(dir: &Path) -> Option<PathBuf> {
+    let mut entries: Vec<_> = fs::read_dir(dir).ok()?.filter_map(|e| e.ok()).map(|e| e.path()).collect();
+    entries.sort();
+    entries.into_iter().find_map(|p| if p.is_dir() { first_file(&p) } else { Some(p) })
+}
+
+/// Removes the directories a deleted ref leaves empty, stopping at
+/// `refs/<kind>` itself.
+pub fn remove_empty_ref_dirs(repo_path: &Path, path: &Path) {
+    let refs_dir = repo_path.join("refs");
+    for dir in path.ancestors().skip(1) {
+        if dir.parent() == Some(refs_dir.as_path()) || !dir.starts_with(&refs_dir) || fs::remove_dir(dir).is_err() {
+            break;
+        }
+    }
+}
+
+/// Points a ref such as `refs/heads/main` (or `HEAD`) at a new hash,
+/// optionally only if it currently holds `expected`, recording `message`
+/// in the reflog.
+pub fn update_ref(repo_path: &Path, name: &str, new: &str, expected: Option<&str>, message: &str) -> io::Result<()> {
+    change_ref(repo_path, name, Some(new), expected, message)
+}
+
+/// Deletes a ref, optionally only if it currently holds `expected`.
+pub fn delete_ref(repo_path: &Path, name: &str, expected: Option<&str>) -> io::Result<()> {
+    change_ref(repo_path, name, None, expected, "")
+}
+
+/// Update HEAD to point at new commit hash: the current branch, or HEAD
+/// itself when detached.
+pub fn update_head(repo_path: &Path, commit_hash: &str, message: &str) -> io::Result<()> {
+    update_ref(repo_path, "HEAD", commit_hash, None, message)
+}
+
 /// Compress and write object data to disk
 pub fn write_compressed(data: &[u8], object_path: &Path) -> io::Result<()> {
     let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
@@ -192,3 +715,91 @@ pub fn read_object(repo_path: &Path, hash: &str) -> io::Result<Vec<u8>> {
         Ok(data) // fallback: no header found
     }
 }
+
+/// Reads a commit object and returns its root tree hash.
+pub fn get_tree_hash(repo_root: &Path, hash: &str) -> io::Result<String> {
+    let commit_data = read_object(repo_root, hash)?;
+    let content = String::from_utf8_lossy(&commit_data);
+    content
+        .lines()
+        .find(|line| line.starts_with("tree "))
+        .map(|line| line[5..].to_string())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Commit missing tree hash"))
+}
+
+/// Parses a tree object into a map of {path -> (mode, hash)}, descending into
+/// subtrees so that every file appears with its full path.
+pub fn read_tree_entries(repo_root: &Path, tree_hash: &str) -> io::Result<HashMap<PathBuf, (String, String)>> {
+    let mut entries = HashMap::new();
+    collect_tree_entries(repo_root, tree_hash, Path::new(""), &mut entries)?;
+    Ok(entries)
+}
+
+fn collect_tree_entries(
+    repo_root: &Path,
+    tree_hash: &str,
+    prefix: &Path,
+    entries: &mut HashMap<PathBuf, (String, String)>,
+) -> io::Result<()> {
+    for (mode, filename, sha1) in read_tree(repo_root, tree_hash)? {
+        let path = prefix.join(filename);
+        if mode == "40000" {
+            collect_tree_entries(repo_root, &sha1, &path, entries)?;
+        } else {
+            entries.insert(path, (mode, sha1));
+        }
+    }
+    Ok(())
+}
+
+/// Parses the direct entries of a tree object as `(mode, name, hash)`.
+pub fn read_tree(repo_root: &Path, tree_hash: &str) -> io::Result<Vec<(String, String, String)>> {
+    let tree_data = read_object(repo_root, tree_hash)?;
+    let mut entries = Vec::new();
+    let mut pos = 0;
+    while pos < tree_data.len() {
+        let mut end = pos;
+        while tree_data[end] != 0 { end += 1; }
+        let entry_str = String::from_utf8_lossy(&tree_data[pos..end]);
+        let (mode, filename) = entry_str.split_once(' ').unwrap();
+        let sha_start = end + 1;
+        let sha_end = sha_start + 20;
+        let sha1 = hex::encode(&tree_data[sha_start..sha_end]);
+        entries.push((mode.to_string(), filename.to_string(), sha1));
+        pos = sha_end;
+    }
+    Ok(entries)
+}
+
+/// Looks up `path` inside a tree, returning the `(mode, hash)` of the blob or
+/// subtree found there. An empty path is the tree itself.
+pub fn find_tree_entry(repo_root: &Path, tree_hash: &str, path: &Path) -> io::Result<Option<(String, String)>> {
+    let mut current = ("40000".to_string(), tree_hash.to_string());
+    for component in path.components() {
+        let name = component.as_os_str().to_string_lossy();
+        if current.0 != "40000" {
+            return Ok(None);
+        }
+        match read_tree(repo_root, &current.1)?.into_iter().find(|(_, entry, _)| *entry == name) {
+            Some((mode, _, hash)) => current = (mode, hash),
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+/// The type of a stored object: `blob`, `tree`, `commit` or `tag`. Commits
+/// written without a `commit <size>` header are recognised by their content.
+pub fn object_type(repo_path: &Path, hash: &str) -> io::Result<String> {
+    let data = read_full_object(repo_path, hash)?;
+    if let Some(nul) = data.iter().position(|&b| b == 0) {
+        let header = String::from_utf8_lossy(&data[..nul]);
+        if let Some((kind, size)) = header.split_once(' ')
+            && !size.is_empty()
+            && size.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Ok(kind.to_string());
+        }
+    }
+    Ok("commit".to_string())
+}
@@ -0,0 +1,11 @@
+use super::commit::{read_index, write_tree};
+use super::utils::find_repo_root;
+use std::io;
+
+/// Writes the index out as tree objects and prints the root tree's hash.
+pub fn run() -> io::Result<()> {
+    let repo_path = find_repo_root()?;
+    let entries = read_index(&repo_path)?;
+    println!("{}", write_tree(&repo_path, &entries)?);
+    Ok(())
+}
@@ -4,7 +4,7 @@ mod cli;
 mod commands; // Add this module
 
 use clap::Parser;
-use cli::{Cli, Commands};
+use cli::{Cli, Commands, ReflogAction};
 
 fn main() {
     let cli = Cli::parse();
@@ -12,19 +12,123 @@ fn main() {
     let result = match &cli.command {
         Commands::Init => commands::init::run(),
         Commands::Add { path } => commands::add::run(path),
-        Commands::Commit { message } => commands::commit::run(message),
-        Commands::Log => commands::log::run(),
+        Commands::Commit { message, file, no_edit, all, amend, allow_empty, fixup, squash, author, date, signoff, trailers } => {
+            let options = commands::commit::CommitOptions {
+                message: message.clone(),
+                file: file.clone(),
+                no_edit: *no_edit,
+                amend: *amend,
+                allow_empty: *allow_empty,
+                fixup: fixup.clone(),
+                squash: squash.clone(),
+                all: *all,
+                author: author.clone(),
+                date: date.clone(),
+                signoff: *signoff,
+                trailers: trailers.clone(),
+            };
+            commands::commit::run(&options)
+        }
+        Commands::Log {
+            oneline,
+            max_count,
+            graph,
+            all,
+            first_parent,
+            format,
+            patch,
+            stat,
+            author,
+            grep,
+            since,
+            until,
+            revisions,
+            paths,
+            name_status,
+            follow,
+            renames,
+        } => {
+            let options = commands::log::LogOptions {
+                name_status: *name_status,
+                find_renames: renames.find_renames,
+                find_copies: renames.find_copies,
+                follow: follow.clone(),
+                format: commit_format(format, *oneline),
+                patch: *patch,
+                stat: *stat,
+                max_count: *max_count,
+                graph: *graph,
+                all: *all,
+                first_parent: *first_parent,
+                revisions: revisions.clone(),
+                paths: paths.clone(),
+                author: author.clone(),
+                grep: grep.clone(),
+                since: *since,
+                until: *until,
+            };
+            commands::log::run(&options)
+        }
+        Commands::Show { revision, format, oneline, stat } => {
+            commands::show::run(revision, &commit_format(format, *oneline), *stat)
+        }
         Commands::LsTree { hash } => commands::ls_tree::run(hash),
-        Commands::Checkout { target } => commands::checkout::run(target),
-        Commands::Status => commands::status::run(),
+        Commands::Checkout { target, new_branch, force_create, detach } => {
+            let options = commands::checkout::CheckoutOptions {
+                force_create: force_create.is_some(),
+                new_branch: new_branch.clone().or_else(|| force_create.clone()),
+                detach: *detach,
+            };
+            commands::checkout::run(target.as_deref(), &options)
+        }
+        Commands::Switch { target, create, force_create, detach } => {
+            let options = commands::switch::SwitchOptions {
+                force_create: force_create.is_some(),
+                create: create.clone().or_else(|| force_create.clone()),
+                detach: *detach,
+            };
+            commands::switch::run(target.as_deref(), &options)
+        }
+        Commands::Tag { name, target, annotate, message, delete, list, force } => {
+            let options = commands::tag::TagOptions {
+                name: name.clone(),
+                target: target.clone(),
+                annotate: *annotate,
+                message: message.clone(),
+                delete: *delete,
+                list: *list,
+                force: *force,
+            };
+            commands::tag::run(&options)
+        }
+        Commands::Describe { revision, tags, long, dirty, always } => {
+            let options = commands::describe::DescribeOptions {
+                revision: revision.clone(),
+                tags: *tags,
+                long: *long,
+                dirty: dirty.clone(),
+                always: *always,
+            };
+            commands::describe::run(&options)
+        }
+        Commands::Status { renames } => {
+            commands::status::run(renames.find_renames, renames.find_copies)
+        }
 
-        Commands::Branch { create } => {
-            // Check if the -c flag was used
-            if let Some(branch_name) = create {
-                commands::branch::run(Some(branch_name), true) // Create branch
-            } else {
-                commands::branch::run(None, false) // List branches
-            }
+        Commands::Branch { names, create, delete, force_delete, rename, force, verbose, merged, no_merged } => {
+            // -c NAME is kept as a spelling of `rit branch NAME`
+            let names = create.iter().chain(names).cloned().collect();
+            let options = commands::branch::BranchOptions {
+                names,
+                delete: *delete,
+                force_delete: *force_delete,
+                rename: *rename,
+                force: *force,
+                verbose: *verbose,
+                merged: merged.clone(),
+                no_merged: no_merged.clone(),
+            };
+            commands::branch::run(&options)
         }
 
         Commands::Config { key, value } => {
@@ -36,9 +140,128 @@ fn main() {
         // In src/main.rs -> inside the match &cli.command { ... } block
         Commands::Remove { path, cached } => commands::remove::run(path, *cached),
 
-        Commands::Diff { path } => commands::diff::run(path.as_deref()),
+        Commands::Diff {
+            path,
+            stat,
+            patch,
+            numstat,
+            shortstat,
+            name_only,
+            name_status,
+            binary,
+            word_diff,
+            word_diff_regex,
+            color_words,
+            color_moved,
+            cached,
+            renames,
+        } => {
+            // --color-words is shorthand for --word-diff=color with an optional regex
+            let (word_diff, word_regex) = match color_words {
+                Some(regex) => (
+                    Some(commands::diff::WordDiffMode::Color),
+                    Some(regex.clone()).filter(|r| !r.is_empty()).or(word_diff_regex.clone()),
+                ),
+                // --word-diff-regex on its own implies --word-diff=plain
+                None => {
+                    let implied = word_diff_regex.as_ref().map(|_| commands::diff::WordDiffMode::Plain);
+                    (word_diff.or(implied), word_diff_regex.clone())
+                }
+            };
+            let options = commands::diff::DiffOptions {
+                stat: *stat,
+                numstat: *numstat,
+                shortstat: *shortstat,
+                name_only: *name_only,
+                name_status: *name_status,
+                binary: *binary,
+                word_diff,
+                word_regex,
+                color_moved: *color_moved,
+                find_renames: renames.find_renames,
+                find_copies: renames.find_copies,
+                cached: *cached,
+                patch: *patch,
+            };
+            commands::diff::run(path.as_deref(), &options)
+        }
         
         Commands::Merge { branch } => commands::merge::run(branch),
+
+        Commands::Difftool { path, tool, cached } => {
+            commands::difftool::run(path.as_deref(), tool.as_deref(), *cached)
+        }
+
+        Commands::Mergetool { paths, tool } => commands::mergetool::run(paths, tool.as_deref()),
+
+        Commands::CatFile { show_type, size, pretty, exists, batch, object } => {
+            use commands::cat_file::CatMode;
+            let mode = [(*show_type, CatMode::Type), (*size, CatMode::Size), (*pretty, CatMode::Pretty), (*exists, CatMode::Exists)]
+                .into_iter()
+                .find_map(|(set, mode)| set.then_some(mode));
+            commands::cat_file::run(object.as_deref(), mode, *batch)
+        }
+
+        Commands::HashObject { write, stdin, files } => commands::hash_object::run(files, *stdin, *write),
+
+        Commands::WriteTree => commands::write_tree::run(),
+
+        Commands::CommitTree { tree, parents, message } => {
+            commands::commit_tree::run(tree, parents, message.as_deref())
+        }
+
+        Commands::UpdateRef { delete, message, refname, new_value, old_value } => {
+            // With -d the only value given is the expected old one
+            let (new, old) = match delete {
+                true => (None, new_value.as_deref()),
+                false => (new_value.as_deref(), old_value.as_deref()),
+            };
+            commands::update_ref::run(refname, new, old, *delete, message.as_deref().unwrap_or(""))
+        }
+
+        Commands::RevParse { short, verify, abbrev_ref, show_toplevel, git_dir, revisions } => {
+            let options = commands::rev_parse::RevParseOptions {
+                short: *short,
+                verify: *verify,
+                abbrev_ref: *abbrev_ref,
+                show_toplevel: *show_toplevel,
+                git_dir: *git_dir,
+            };
+            commands::rev_parse::run(revisions, &options)
+        }
+
+        Commands::Reflog { action, refname, max_count } => match action {
+            None => commands::reflog::show(refname.as_deref(), *max_count),
+            Some(ReflogAction::Show { refname, max_count }) => commands::reflog::show(refname.as_deref(), *max_count),
+            Some(ReflogAction::Expire { expire, all, refs }) => commands::reflog::expire(refs, *all, expire.as_deref()),
+            Some(ReflogAction::Delete { entries }) => commands::reflog::delete(entries),
+        },
+
+        Commands::SymbolicRef { short, quiet, delete, message, name, target } => {
+            let options = commands::symbolic_ref::SymbolicRefOptions {
+                short: *short,
+                quiet: *quiet,
+                delete: *delete,
+                message: message.clone(),
+            };
+            commands::symbolic_ref::run(name, target.as_deref(), &options)
+        }
+
+        Commands::PackRefs { all, no_prune } => commands::pack_refs::run(*all, *no_prune),
+
+        Commands::CheckRefFormat { branch, allow_onelevel, normalize, name } => {
+            commands::check_ref_format::run(name, *branch, *allow_onelevel, *normalize)
+        }
+        Commands::InterpretTrailers { files, trailers, if_exists, parse, only_trailers, in_place } => {
+            let options = commands::interpret_trailers::InterpretTrailersOptions {
+                trailers: trailers.clone(),
+                if_exists: *if_exists,
+                parse: *parse,
+                only_trailers: *only_trailers,
+                in_place: *in_place,
+            };
+            commands::interpret_trailers::run(files, &options)
+        }
     };
 
     if let Err(e) = result {
@@ -46,3 +269,18 @@ fn main() {
         std::process::exit(1); // ADD THIS LINE
     }
 }
+
+/// Builds the commit layout from the shared format flags; `--oneline` is
+/// shorthand for `--pretty=oneline --abbrev-commit`.
+fn commit_format(args: &cli::FormatArgs, oneline: bool) -> commands::log::CommitFormat {
+    let pretty = match (&args.pretty, oneline) {
+        (Some(pretty), _) => pretty.clone(),
+        (None, true) => commands::log::Pretty::Oneline,
+        (None, false) => commands::log::Pretty::Medium,
+    };
+    commands::log::CommitFormat {
+        pretty,
+        date: args.date.unwrap_or_default(),
+        abbrev_commit: args.abbrev_commit || oneline,
+    }
+}